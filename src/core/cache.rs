@@ -0,0 +1,76 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL - Output Cache
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! Node output cache with optional least-recently-used eviction.
+
+use std::collections::HashMap;
+
+/// String-keyed cache. Unbounded by default; with a capacity, inserting past
+/// the limit evicts the least recently used entry.
+#[derive(Debug, Clone)]
+pub struct LruCache<V> {
+    entries: HashMap<String, (V, u64)>,
+    capacity: Option<usize>,
+    clock: u64,
+}
+
+impl<V: Clone> LruCache<V> {
+    pub fn new(capacity: Option<usize>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            clock: 0,
+        }
+    }
+
+    /// Changes the capacity, evicting entries if the cache is now over it.
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+        self.evict_to_capacity();
+    }
+
+    /// Returns a copy of the entry and marks it as most recently used.
+    pub fn get(&mut self, key: &str) -> Option<V> {
+        self.clock += 1;
+        let clock: u64 = self.clock;
+        self.entries.get_mut(key).map(|(value, used): &mut (V, u64)| {
+            *used = clock;
+            value.clone()
+        })
+    }
+
+    pub fn insert(&mut self, key: String, value: V) {
+        self.clock += 1;
+        let _: Option<(V, u64)> = self.entries.insert(key, (value, self.clock));
+        self.evict_to_capacity();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn evict_to_capacity(&mut self) {
+        let Some(capacity) = self.capacity else { return };
+        while self.entries.len() > capacity {
+            let oldest: Option<String> = self.entries.iter()
+                .min_by_key(|(_, (_, used)): &(&String, &(V, u64))| *used)
+                .map(|(key, _): (&String, &(V, u64))| key.clone());
+            match oldest {
+                Some(key) => {
+                    let _: Option<(V, u64)> = self.entries.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+}
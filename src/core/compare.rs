@@ -0,0 +1,72 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL - Value Comparison
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! Shared equality and ordering semantics for runtime values.
+//!
+//! Every operation that compares values goes through these helpers so that
+//! `1` and `1.0` are treated the same everywhere.
+
+use serde_json::{Number, Value};
+use std::cmp::Ordering;
+
+/// Orders two numbers by value. Two integers compare exactly, even beyond the
+/// 2^53 range where `f64` loses precision; anything else compares as `f64`,
+/// so `-0.0 == 0`. Returns `None` when either side has no `f64` value.
+pub fn compare_numbers(n1: &Number, n2: &Number) -> Option<Ordering> {
+    match (n1.as_i64(), n2.as_i64(), n1.as_u64(), n2.as_u64()) {
+        (Some(a), Some(b), _, _) => Some(a.cmp(&b)),
+        (_, _, Some(a), Some(b)) => Some(a.cmp(&b)),
+        // One side exceeds i64::MAX and the other is negative
+        (Some(_), None, _, Some(_)) => Some(Ordering::Less),
+        (None, Some(_), Some(_), _) => Some(Ordering::Greater),
+        _ => n1.as_f64()?.partial_cmp(&n2.as_f64()?),
+    }
+}
+
+/// Writes a number in one canonical form, so numbers equal under
+/// [`compare_numbers`] print the same: integers exactly, and floats with no
+/// fractional part as the integer they hold (`1.0` as `1`, `-0.0` as `0`).
+/// Other floats use their shortest round-trip form. The one mismatch is an
+/// integer beyond 2^53 against the float it rounds to, which compare equal
+/// as `f64` but print differently.
+pub fn canonical_number(n: &Number) -> String {
+    if n.is_i64() || n.is_u64() {
+        return n.to_string();
+    }
+    let two_pow_63: f64 = 2.0_f64.powi(63);
+    match n.as_f64() {
+        Some(f) if f.fract() == 0.0_f64 && (-two_pow_63..two_pow_63).contains(&f) => (f as i64).to_string(),
+        Some(f) if f.fract() == 0.0_f64 && (0.0_f64..2.0 * two_pow_63).contains(&f) => (f as u64).to_string(),
+        _ => n.to_string(),
+    }
+}
+
+/// Numeric-aware structural equality.
+/// Numbers compare by value (`1 == 1.0`); NaN is never equal to anything.
+/// Arrays and objects compare element-wise with the same rules.
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(n1), Value::Number(n2)) => compare_numbers(n1, n2) == Some(Ordering::Equal),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(i, j): (&Value, &Value)| values_equal(i, j))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter().all(|(k, v): (&String, &Value)| y.get(k).is_some_and(|w: &Value| values_equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Orders two values of the same comparable kind (numbers, strings, booleans).
+/// Returns `None` for mixed or non-comparable values.
+pub fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(n1), Value::Number(n2)) => compare_numbers(n1, n2),
+        (Value::String(s1), Value::String(s2)) => Some(s1.cmp(s2)),
+        (Value::Bool(b1), Value::Bool(b2)) => Some(b1.cmp(b2)),
+        _ => None,
+    }
+}
@@ -0,0 +1,156 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL - Graph Diff
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! Structural comparison of two graph versions.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use serde_json::Value;
+use super::schema::{Graph, Node, RetryPolicy};
+
+/// A change to one argument (or a field such as `returns`) of a node present in both graphs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgChange {
+    pub port: String,
+    /// Value in the original graph (`None` if the port was added).
+    pub old: Option<Value>,
+    /// Value in the other graph (`None` if the port was removed).
+    pub new: Option<Value>,
+}
+
+/// Differences between two graphs. All lists are sorted by node id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// `(node, old op, new op)`
+    pub op_changes: Vec<(String, String, String)>,
+    /// `(node, changes)` for nodes whose arguments differ
+    pub arg_changes: Vec<(String, Vec<ArgChange>)>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+            && self.op_changes.is_empty() && self.arg_changes.is_empty()
+    }
+}
+
+impl Graph {
+    /// Compares this graph against `other`, reporting what `other` changed.
+    pub fn diff(&self, other: &Graph) -> GraphDiff {
+        let ids: BTreeSet<&String> = self.nodes.keys().chain(other.nodes.keys()).collect();
+        let mut diff: GraphDiff = GraphDiff::default();
+
+        for id in ids {
+            let (old, new): (&Node, &Node) = match (self.nodes.get(id), other.nodes.get(id)) {
+                (Some(old), Some(new)) => (old, new),
+                (None, Some(_)) => { diff.added.push(id.clone()); continue; }
+                (Some(_), None) => { diff.removed.push(id.clone()); continue; }
+                (None, None) => continue,
+            };
+
+            if old.op != new.op {
+                diff.op_changes.push((id.clone(), old.op.clone(), new.op.clone()));
+            }
+
+            let mut changes: Vec<ArgChange> = Vec::new();
+            let returns = |node: &Node| -> Option<Value> { node.returns.as_ref().map(|t| Value::String(t.to_string())) };
+            if returns(old) != returns(new) {
+                changes.push(ArgChange { port: "returns".to_string(), old: returns(old), new: returns(new) });
+            }
+            if old.desc != new.desc {
+                let desc = |node: &Node| -> Option<Value> { node.desc.clone().map(Value::String) };
+                changes.push(ArgChange { port: "desc".to_string(), old: desc(old), new: desc(new) });
+            }
+            if old.cache != new.cache {
+                let cache = |node: &Node| -> Option<Value> { node.cache.map(Value::Bool) };
+                changes.push(ArgChange { port: "cache".to_string(), old: cache(old), new: cache(new) });
+            }
+            if old.retry != new.retry {
+                let retry = |node: &Node| -> Option<Value> { node.retry.and_then(|r: RetryPolicy| serde_json::to_value(r).ok()) };
+                changes.push(ArgChange { port: "retry".to_string(), old: retry(old), new: retry(new) });
+            }
+
+            let ports: BTreeSet<&String> = old.args.keys().chain(new.args.keys()).collect();
+            for port in ports {
+                let (a, b): (Option<&Value>, Option<&Value>) = (old.args.get(port), new.args.get(port));
+                if a != b {
+                    changes.push(ArgChange { port: port.clone(), old: a.cloned(), new: b.cloned() });
+                }
+            }
+
+            if !changes.is_empty() {
+                diff.arg_changes.push((id.clone(), changes));
+            }
+        }
+
+        diff
+    }
+}
+
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        for id in &self.added {
+            writeln!(f, "+ {}", id)?;
+        }
+        for id in &self.removed {
+            writeln!(f, "- {}", id)?;
+        }
+        for (id, old, new) in &self.op_changes {
+            writeln!(f, "~ {}: op {} -> {}", id, old, new)?;
+        }
+        for (id, changes) in &self.arg_changes {
+            for change in changes {
+                let show = |v: &Option<Value>| -> String {
+                    v.as_ref().map(|v: &Value| v.to_string()).unwrap_or_else(|| "(none)".to_string())
+                };
+                writeln!(f, "~ {}.{}: {} -> {}", id, change.port, show(&change.old), show(&change.new))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_each_kind_of_change() {
+        let old: Graph = Graph::from_json_str(r#"{
+            "a": { "op": "Const", "value": { "literal": 1, "type": "Number" } },
+            "b": { "op": "Add", "a": { "ref": "a", "type": "Number" }, "b": { "literal": 1, "type": "Number" } },
+            "gone": { "op": "Const", "value": { "literal": 0, "type": "Number" } }
+        }"#).unwrap();
+        let new: Graph = Graph::from_json_str(r#"{
+            "a": { "op": "Const", "value": { "literal": 1, "type": "Number" } },
+            "b": { "op": "Mul", "a": { "ref": "a", "type": "Number" }, "b": { "literal": 2, "type": "Number" } },
+            "fresh": { "op": "Const", "value": { "literal": 0, "type": "Number" } }
+        }"#).unwrap();
+
+        let diff: GraphDiff = old.diff(&new);
+        assert_eq!(diff.added, vec!["fresh".to_string()]);
+        assert_eq!(diff.removed, vec!["gone".to_string()]);
+        assert_eq!(diff.op_changes, vec![("b".to_string(), "Add".to_string(), "Mul".to_string())]);
+        assert_eq!(diff.arg_changes.len(), 1);
+        let (node, changes): &(String, Vec<ArgChange>) = &diff.arg_changes[0];
+        assert_eq!(node, "b");
+        assert_eq!(changes[0].port, "b");
+        assert_eq!(changes[0].new, Some(serde_json::json!({ "literal": 2, "type": "Number" })));
+        assert!(diff.to_string().contains("~ b: op Add -> Mul"), "{}", diff);
+    }
+
+    #[test]
+    fn identical_graphs_have_no_diff() {
+        let graph: Graph = Graph::from_json_str(r#"{ "a": { "op": "Const", "value": { "literal": 1, "type": "Number" } } }"#).unwrap();
+        let diff: GraphDiff = graph.diff(&graph.clone());
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No differences\n");
+    }
+}
@@ -5,129 +5,727 @@
 
 //! SPELL execution engine.
 //!
-//! All types MUST be explicitly declared - no inference, no legacy support.
+//! All types MUST be explicitly declared by default; literal inference is opt-in.
 
-use super::schema::{Graph, Node};
+use super::schema::{Graph, Node, RetryPolicy};
 use super::types::{SpellType, TypedValue};
-use super::ops::Ops;
+use super::ops::{Context, Ops, PrintSink, ProgressFn};
+use super::format::NumberFormat;
+use super::cache::LruCache;
+use super::metrics::OpMetrics;
+use super::limit::ConcurrencyLimit;
+use super::lint::LintWarning;
 use super::error::{Error, Result};
-use std::collections::{HashMap, HashSet};
+use std::cell::{RefCell, RefMut};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Id of the node whose value is the graph's result when no output node is set.
+pub const OUTPUT_NODE: &str = "@output";
 
 /// SPELL execution engine.
 pub struct Engine {
     graph: Graph,
-    cache: HashMap<String, Value>,
-    type_cache: HashMap<String, SpellType>,
+    /// Output maps are shared, so cache hits don't deep-copy large values.
+    cache: LruCache<Arc<HashMap<String, Value>>>,
+    base_dir: Option<PathBuf>,
+    allow_insecure_http: bool,
+    progress: Option<ProgressFn>,
+    infer_literals: bool,
+    ops: Arc<Ops>,
+    number_format: NumberFormat,
+    print_sink: Option<PrintSink>,
+    verbose_errors: bool,
+    metrics: HashMap<String, OpMetrics>,
+    inputs: Arc<HashMap<String, Value>>,
+    external_limit: Option<Arc<ConcurrencyLimit>>,
+    output: Option<String>,
+    deadline: Option<Duration>,
+    /// When the first node of the current run executed, for measuring the deadline.
+    started: Option<Instant>,
+    /// Created for a nested graph, sharing the calling run's deadline clock.
+    nested: bool,
+    /// Set while explaining: operations with side effects are not executed,
+    /// here or in the operations and nested graphs a node runs.
+    dry_run: bool,
+    on_node_start: Option<NodeStartFn>,
+    on_node_complete: Option<NodeCompleteFn>,
+}
+
+/// Callback invoked before a node's operation executes, with
+/// `(node_id, op, inputs)`.
+pub type NodeStartFn = Arc<dyn Fn(&str, &str, &HashMap<String, Value>) + Send + Sync>;
+
+/// Callback invoked after a node's operation executes, with
+/// `(node_id, op, inputs, outputs or error)`.
+pub type NodeCompleteFn = Arc<dyn Fn(&str, &str, &HashMap<String, Value>, &Result<HashMap<String, Value>>) + Send + Sync>;
+
+/// The inputs one node would receive, as reported by `Engine::explain`.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub node: String,
+    pub op: String,
+    /// `(port, declared type, resolved value)` sorted by port. A port that
+    /// could not be resolved holds the error instead of a value.
+    pub inputs: Vec<(String, Option<SpellType>, Result<Value>)>,
 }
 
 impl Engine {
     pub fn new(graph: Graph) -> Self {
         Self {
             graph,
-            cache: HashMap::new(),
-            type_cache: HashMap::new(),
+            cache: LruCache::new(None),
+            base_dir: None,
+            allow_insecure_http: false,
+            progress: None,
+            infer_literals: false,
+            ops: Arc::new(Ops::default()),
+            number_format: NumberFormat::default(),
+            print_sink: None,
+            verbose_errors: false,
+            metrics: HashMap::new(),
+            inputs: Arc::new(HashMap::new()),
+            external_limit: None,
+            output: None,
+            deadline: None,
+            started: None,
+            nested: false,
+            dry_run: false,
+            on_node_start: None,
+            on_node_complete: None,
+        }
+    }
+
+    /// Parses a graph from JSON source and creates an engine for it.
+    pub fn from_json_str(src: &str) -> Result<Self> {
+        Graph::from_json_str(src).map(Engine::new)
+    }
+
+    /// Creates an engine for a nested graph that inherits the settings and
+    /// operation registry of the calling node's context.
+    pub fn from_context(graph: Graph, ctx: &Context) -> Self {
+        Self {
+            base_dir: ctx.base_dir.clone(),
+            allow_insecure_http: ctx.allow_insecure_http,
+            progress: ctx.progress.clone(),
+            ops: ctx.ops.clone(),
+            number_format: ctx.number_format.clone(),
+            print_sink: ctx.print_sink.clone(),
+            inputs: ctx.inputs.clone(),
+            external_limit: ctx.external_limit.clone(),
+            deadline: ctx.deadline.map(|(_, limit): (Instant, Duration)| limit),
+            started: ctx.deadline.map(|(started, _): (Instant, Duration)| started),
+            nested: true,
+            dry_run: ctx.dry_run,
+            on_node_start: ctx.on_node_start.clone(),
+            on_node_complete: ctx.on_node_complete.clone(),
+            ..Engine::new(graph)
         }
     }
 
-    /// Executes all nodes in the graph.
-    pub fn run(&mut self) -> () {
-        let node_ids: Vec<String> = self.graph.nodes.keys().cloned().collect();
+    /// Injects a runtime value that `Input` nodes named `name` return.
+    /// Cached outputs are discarded, since they may depend on the old value.
+    pub fn set_input(&mut self, name: impl Into<String>, value: Value) {
+        let _: Option<Value> = Arc::make_mut(&mut self.inputs).insert(name.into(), value);
+        self.cache.clear();
+    }
+
+    /// Sets how numbers are rendered when values are printed.
+    pub fn with_number_format(mut self, format: NumberFormat) -> Self {
+        self.number_format = format;
+        self
+    }
+
+    /// Sends `Print` output to `sink` instead of standard output, also from
+    /// nested graphs. Keep a clone of the sink to read what was written.
+    pub fn with_print_sink(mut self, sink: PrintSink) -> Self {
+        self.print_sink = Some(sink);
+        self
+    }
+
+    /// Includes extra diagnostics (such as an operation's inputs) in errors reported by `run`.
+    pub fn with_verbose_errors(mut self, verbose: bool) -> Self {
+        self.verbose_errors = verbose;
+        self
+    }
+
+    /// Bounds the output cache to `capacity` nodes, evicting the least recently
+    /// used outputs first. Evicted nodes are recomputed on their next access.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache.set_capacity(Some(capacity));
+        self
+    }
+
+    /// Runs at most `max` external operations (such as HTTP requests) at once.
+    pub fn with_max_external_concurrency(self, max: usize) -> Self {
+        self.with_external_limit(Arc::new(ConcurrencyLimit::new(max)))
+    }
+
+    /// Shares an external concurrency limit, e.g. across engines running on
+    /// several threads.
+    pub fn with_external_limit(mut self, limit: Arc<ConcurrencyLimit>) -> Self {
+        self.external_limit = Some(limit);
+        self
+    }
+
+    /// Limits the execution time of each run (`run_collect`, `run_stream`,
+    /// `result`, `evaluate`, ...). The clock starts when the run's first node
+    /// executes; once `limit` has elapsed, every node still to run fails with
+    /// `Error::Timeout` and `run_collect` stops. Nested graphs, such as `While`
+    /// bodies, share the clock of the node running them, and retry backoff
+    /// never sleeps past the deadline.
+    pub fn with_deadline(mut self, limit: Duration) -> Self {
+        self.deadline = Some(limit);
+        self
+    }
+
+    /// Designates the node whose value `result` returns, overriding the
+    /// `@output` node convention.
+    pub fn with_output(mut self, node_id: impl Into<String>) -> Self {
+        self.output = Some(node_id.into());
+        self
+    }
+
+    /// Replaces the operation registry, e.g. one extended with custom operations.
+    pub fn with_ops(mut self, ops: Ops) -> Self {
+        self.ops = Arc::new(ops);
+        self
+    }
+
+    /// Confines filesystem operations to `dir` instead of the working directory.
+    pub fn with_base_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = Some(dir.into());
+        self
+    }
+
+    /// Lets network operations fetch plain-HTTP URLs and private addresses.
+    pub fn with_insecure_http(mut self, allow: bool) -> Self {
+        self.allow_insecure_http = allow;
+        self
+    }
+
+    /// Accepts literals without a `type`, inferring it from the value.
+    /// References still require explicit types.
+    pub fn with_literal_inference(mut self, infer: bool) -> Self {
+        self.infer_literals = infer;
+        self
+    }
+
+    /// Installs a callback that array operations invoke after each element
+    /// with `(node_id, processed, total)`.
+    pub fn with_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str, usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Installs a callback invoked with `(node_id, op, inputs)` just before a
+    /// node's operation executes. Cache hits do not execute, so they are not reported.
+    /// Nodes of nested graphs, such as a `While` body, are reported under their
+    /// own ids, inside the calling node's start and completion.
+    pub fn on_node_start<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str, &str, &HashMap<String, Value>) + Send + Sync + 'static,
+    {
+        self.on_node_start = Some(Arc::new(callback));
+        self
+    }
+
+    /// Installs a callback invoked with `(node_id, op, inputs, outcome)` once a
+    /// node's operation has executed, after any retries. `outcome` is the
+    /// operation's outputs, before they are checked against `returns`, or its error.
+    pub fn on_node_complete<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str, &str, &HashMap<String, Value>, &Result<HashMap<String, Value>>) + Send + Sync + 'static,
+    {
+        self.on_node_complete = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call counts and execution times per operation name, accumulated over
+    /// every evaluation so far. Cache hits are not counted.
+    pub fn metrics(&self) -> &HashMap<String, OpMetrics> {
+        &self.metrics
+    }
+
+    /// Executes the graph as `run_collect` does, reporting the errors on stderr.
+    pub fn run(&mut self) {
+        for e in self.run_collect() {
+            self.report(&e);
+        }
+    }
+
+    /// Executes the graph by pulling results from its sink nodes and returns
+    /// every error encountered, in evaluation order.
+    /// Nodes are evaluated only when a consumer needs them, so inputs an
+    /// operation skips (e.g. an unchosen Switch branch) never run. Nodes not
+    /// reachable from any sink (only possible inside cycles) are evaluated
+    /// afterwards so the cycle is reported.
+    pub fn run_collect(&mut self) -> Vec<Error> {
+        self.begin_run();
+        if let Err(e) = self.validate() {
+            return vec![e];
+        }
+
+        let mut node_ids: Vec<String> = self.graph.sinks();
+        let mut reachable: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = node_ids.clone();
+        while let Some(id) = stack.pop() {
+            if reachable.insert(id.clone()) {
+                if let Some(node) = self.graph.nodes.get(&id) {
+                    stack.extend(node.dependencies());
+                }
+            }
+        }
+        node_ids.extend(
+            self.graph.topological_order()
+                .into_iter()
+                .filter(|id: &String| !reachable.contains(id)),
+        );
         
+        let mut errors: Vec<Error> = Vec::new();
         for node_id in node_ids {
-            let mut visiting: HashSet<String> = HashSet::new();
-            match self.execute_node(&node_id, &mut visiting) {
-                Ok(_) => {},
-                Err(e) => eprintln!("Error: {}", e),
+            let mut visiting: Vec<String> = Vec::new();
+            match self.execute_outputs(&node_id, &mut visiting) {
+                Ok(_) => {}
+                Err(e @ Error::Timeout { .. }) => {
+                    errors.push(e);
+                    break;
+                }
+                Err(e) => errors.push(e),
             }
         }
+        errors
     }
 
-    fn execute_node(&mut self, node_id: &str, visiting: &mut HashSet<String>) -> Result<Value> {
-        // 1. Check Cache
-        if let Some(cached) = self.cache.get(node_id) {
-            return Ok(cached.clone());
+    fn report(&self, e: &Error) {
+        if self.verbose_errors {
+            eprintln!("Error: {:#}", e);
+            if let Some(node) = e.node() {
+                match (self.graph.source_map.file(node), self.graph.source_map.get(node)) {
+                    (Some(file), Some(span)) => eprintln!("  --> {}:{}", file, span),
+                    (None, Some(span)) => eprintln!("  --> {}", span),
+                    _ => {}
+                }
+            }
+        } else {
+            eprintln!("Error: {}", e);
+        }
+    }
+
+    /// Evaluates the graph's sink nodes (nodes nothing else references) one at a
+    /// time, yielding each result as soon as it is computed.
+    ///
+    /// Ordering: sinks are yielded in topological order, ties broken by node id.
+    /// Evaluation is sequential and lazy - a sink runs only when the iterator
+    /// is advanced - so the order is deterministic for a given graph.
+    ///
+    /// A sink's result is its `out` port, or an object of all its ports if it
+    /// has no `out`.
+    pub fn run_stream(&mut self) -> impl Iterator<Item = (String, Result<Value>)> + '_ {
+        self.begin_run();
+        let sinks: Vec<String> = self.graph.sinks();
+        sinks.into_iter().map(move |node_id: String| {
+            let mut visiting: Vec<String> = Vec::new();
+            let result: Result<Value> = self.execute_outputs(&node_id, &mut visiting).map(|ports: Arc<HashMap<String, Value>>| {
+                let mut ports: HashMap<String, Value> = (*ports).clone();
+                ports.remove("out").unwrap_or_else(|| Value::Object(ports.into_iter().collect()))
+            });
+            (node_id, result)
+        })
+    }
+
+    /// Checks that every node's operation is registered, reporting all unknown
+    /// operations together, and that every reference names an output port its
+    /// source's operation declares: `port` for `node:port`, and `out` for a bare `node`.
+    pub fn validate(&self) -> Result<()> {
+        let mut node_ids: Vec<&String> = self.graph.nodes.keys().collect();
+        node_ids.sort();
+
+        let mut unknown: Vec<Error> = node_ids.iter()
+            .filter(|id: &&&String| self.ops.get(&self.graph.nodes[**id].op).is_none())
+            .map(|id: &&String| Error::UnknownOperation { node: id.to_string(), op: self.graph.nodes[*id].op.clone() })
+            .collect();
+        match unknown.len() {
+            0 => {}
+            1 => return Err(unknown.remove(0)),
+            _ => return Err(Error::Multiple(unknown)),
+        }
+
+        for node_id in node_ids {
+            for (_, typed_result) in self.graph.nodes[node_id].get_all_typed_args() {
+                let Ok(typed_value) = typed_result else { continue };
+                let Some(reference) = typed_value.get_reference() else { continue };
+                let (source, port): (&str, &str) = match reference.split_once(':') {
+                    Some((source, port)) if !self.graph.nodes.contains_key(reference) => (source, port),
+                    // Bare references to unknown nodes are reported when evaluated
+                    _ if !self.graph.nodes.contains_key(reference) => continue,
+                    _ => (reference, "out"),
+                };
+
+                let source_node: &Node = self.graph.nodes.get(source)
+                    .ok_or_else(|| Error::NodeNotFound(source.to_string()))?;
+                let Some(op) = self.ops.get(&source_node.op) else { continue };
+
+                if !op.output_ports().contains(&port) {
+                    return Err(Error::UnknownPort {
+                        node: source.to_string(),
+                        port: port.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves every node's inputs, in topological order, without executing
+    /// the node itself. Upstream nodes run as needed to produce values, except
+    /// operations with side effects (e.g. `Print`, `HttpGet`), so inputs that
+    /// depend on one are reported as errors. This holds for operations run on
+    /// a node's behalf too, such as a `Map`'s `apply_op` or a `While` body.
+    /// Errors are recorded per port rather than stopping the walk.
+    pub fn explain(&mut self) -> Vec<Explanation> {
+        self.begin_run();
+        self.dry_run = true;
+        let mut explanations: Vec<Explanation> = Vec::new();
+        for node_id in self.graph.topological_order() {
+            let node: Node = self.graph.nodes[&node_id].clone();
+            let mut inputs: Vec<(String, Option<SpellType>, Result<Value>)> = Vec::new();
+            for (port, typed_result) in node.get_all_typed_args() {
+                let declared: Option<SpellType> = typed_result.as_ref().ok()
+                    .and_then(|typed: &TypedValue| typed.get_type().cloned());
+                let mut visiting: Vec<String> = vec![node_id.clone()];
+                let value: Result<Value> = self.resolve_arg(&node, &node_id, &port, typed_result, &mut visiting);
+                inputs.push((port, declared, value));
+            }
+            explanations.push(Explanation { node: node_id, op: node.op, inputs });
+        }
+        self.dry_run = false;
+        explanations
+    }
+
+    /// Runs the static lints against the graph using this engine's operation registry.
+    pub fn lint(&self) -> Vec<LintWarning> {
+        super::lint::lint(&self.graph, &self.ops)
+    }
+
+    /// Evaluates the graph as a single expression, returning the value of the
+    /// node set with `with_output`, or else of the node with id `@output`.
+    pub fn result(&mut self) -> Result<Value> {
+        self.validate()?;
+        let node_id: String = self.output.clone().unwrap_or_else(|| OUTPUT_NODE.to_string());
+        self.evaluate(&node_id)
+    }
+
+    /// Evaluates a single node (and its dependencies), returning its output.
+    pub fn evaluate(&mut self, node_id: &str) -> Result<Value> {
+        self.begin_run();
+        let mut visiting: Vec<String> = Vec::new();
+        self.execute_node(node_id, &mut visiting)
+    }
+
+    /// Evaluates a node and returns every output port it produced, for
+    /// operations with named ports and possibly no `out`.
+    pub fn evaluate_outputs(&mut self, node_id: &str) -> Result<HashMap<String, Value>> {
+        self.begin_run();
+        let mut visiting: Vec<String> = Vec::new();
+        self.execute_outputs(node_id, &mut visiting).map(|ports: Arc<HashMap<String, Value>>| (*ports).clone())
+    }
+
+    /// Restarts the deadline clock for a new run. Nested engines keep the
+    /// clock of the run that created them.
+    fn begin_run(&mut self) {
+        if !self.nested {
+            self.started = None;
+        }
+    }
+
+    /// Executes a node reference, which is either a node id (yielding its
+    /// `out` port) or `node:port` (yielding that output port).
+    fn execute_node(&mut self, node_id: &str, visiting: &mut Vec<String>) -> Result<Value> {
+        let (source, port): (&str, &str) = match node_id.split_once(':') {
+            Some((source, port)) if !self.graph.nodes.contains_key(node_id) => (source, port),
+            _ => (node_id, "out"),
+        };
+
+        let outputs: Arc<HashMap<String, Value>> = self.execute_outputs(source, visiting)?;
+        outputs.get(port).cloned().ok_or_else(|| Error::OperationError {
+            node: source.to_string(),
+            reason: format!("Operation produced no '{}' output", port),
+            inputs_snapshot: None,
+        })
+    }
+
+    /// Executes a node (or reads it from the cache), returning all its output ports.
+    fn execute_outputs(&mut self, node_id: &str, visiting: &mut Vec<String>) -> Result<Arc<HashMap<String, Value>>> {
+        // 1. Check Cache (unless the node opts out)
+        let node_cacheable: bool = self.graph.nodes.get(node_id)
+            .is_none_or(|node: &Node| node.cache != Some(false));
+        if node_cacheable {
+            if let Some(cached) = self.cache.get(node_id) {
+                return Ok(cached);
+            }
         }
 
         // 2. Cycle Detection
-        if visiting.contains(node_id) {
+        if visiting.iter().any(|id: &String| id == node_id) {
             return Err(Error::CycleDetected(node_id.to_string()));
         }
-        let _: bool = visiting.insert(node_id.to_string());
+        // The node leaves the path however it finishes, so a failure that is
+        // retried or tolerated upstream is not mistaken for a cycle later
+        let depth: usize = visiting.len();
+        visiting.push(node_id.to_string());
+        let result: Result<Arc<HashMap<String, Value>>> = self.run_node(node_id, node_cacheable, visiting);
+        visiting.truncate(depth);
+        result
+    }
 
+    /// Steps 3-7 of `execute_outputs`, run while `node_id` is on the visiting path.
+    fn run_node(&mut self, node_id: &str, node_cacheable: bool, visiting: &mut Vec<String>) -> Result<Arc<HashMap<String, Value>>> {
         // 3. Get Node Definition
         let node: Node = self.graph.nodes.get(node_id)
             .ok_or_else(|| Error::NodeNotFound(node_id.to_string()))?
             .clone();
 
-        // 4. Resolve Arguments
+        // 4. Resolve Arguments (lazy inputs only when the operation asks for them)
+        let op: Arc<dyn super::ops::Operation> = self.ops.get(&node.op)
+            .ok_or_else(|| Error::UnknownOperation { node: node_id.to_string(), op: node.op.clone() })?;
+        let lazy: &[&str] = op.lazy_inputs();
+
         let mut resolved_args: HashMap<String, Value> = HashMap::new();
-        let typed_args_results: HashMap<String, Result<TypedValue>> = node.get_all_typed_args();
+        let mut deferred: HashMap<String, Result<TypedValue>> = HashMap::new();
+        // Resolve in port order (after renaming aliases) so side effects upstream
+        // (e.g. Print) fire deterministically
+        let mut typed_args_results: BTreeMap<String, Result<TypedValue>> = BTreeMap::new();
+        let aliases: &[(&str, &str)] = op.input_aliases();
+        for (key, typed_result) in node.get_all_typed_args() {
+            // Infer under the name the graph uses, which an alias renames below
+            let typed_result: Result<TypedValue> = self.infer_literal(&node, &key, typed_result);
+            let key: String = match aliases.iter().find(|(alias, _): &&(&str, &str)| *alias == key.as_str()) {
+                Some((_, canonical)) if node.args.contains_key(*canonical) => {
+                    return Err(Error::OperationError {
+                        node: node_id.to_string(),
+                        reason: format!("input '{}' is an alias of '{}', which is also supplied", key, canonical),
+                        inputs_snapshot: None,
+                    });
+                }
+                Some((_, canonical)) => canonical.to_string(),
+                None => key,
+            };
+            let _: Option<Result<TypedValue>> = typed_args_results.insert(key, typed_result);
+        }
         
         for (key, typed_result) in typed_args_results {
-            let typed_value: TypedValue = typed_result.map_err(|e: Error| -> Error {
-                match e {
-                    Error::MissingTypeAnnotation { port, .. } => 
-                        Error::MissingTypeAnnotation { node: node_id.to_string(), port },
-                    _ => e,
-                }
-            })?;
-            let resolved: Value = self.resolve_typed_value(&typed_value, node_id, &key, visiting)?;
+            if lazy.contains(&key.as_str()) {
+                let _: Option<Result<TypedValue>> = deferred.insert(key, typed_result);
+                continue;
+            }
+            let resolved: Value = self.resolve_arg(&node, node_id, &key, typed_result, visiting)?;
             let _: Option<Value> = resolved_args.insert(key, resolved);
         }
 
+        for key in op.select_lazy_inputs(&resolved_args) {
+            if let Some(typed_result) = deferred.remove(key) {
+                let resolved: Value = self.resolve_arg(&node, node_id, key, typed_result, visiting)?;
+                let _: Option<Value> = resolved_args.insert(key.to_string(), resolved);
+            }
+        }
+
         // 5. Execute Operation
-        let op: Box<dyn super::ops::Operation> = Ops::get(&node.op)
-            .ok_or_else(|| Error::UnknownOperation(node.op.clone()))?;
+        // The deadline is checked after resolving inputs, so time spent on dependencies counts
+        let started: Instant = *self.started.get_or_insert_with(Instant::now);
+        if let Some(limit) = self.deadline {
+            if started.elapsed() > limit {
+                return Err(Error::Timeout { node: node_id.to_string(), limit });
+            }
+        }
+        for port in op.required_inputs() {
+            if !resolved_args.contains_key(*port) {
+                return Err(Error::MissingInput {
+                    node: node_id.to_string(),
+                    port: port.to_string(),
+                });
+            }
+        }
         
-        let result: HashMap<String, Value> = op.execute(&resolved_args)
-            .map_err(|e: Error| -> Error { 
+        if self.dry_run && op.has_side_effects() {
+            return Err(Error::OperationError {
+                node: node_id.to_string(),
+                reason: format!("not executed while explaining: '{}' has side effects", node.op),
+                inputs_snapshot: None,
+            });
+        }
+
+        let base_ctx: Context = Context {
+            node_id: node_id.to_string(),
+            base_dir: self.base_dir.clone(),
+            allow_insecure_http: self.allow_insecure_http,
+            progress: self.progress.clone(),
+            ops: self.ops.clone(),
+            number_format: self.number_format.clone(),
+            print_sink: self.print_sink.clone(),
+            inputs: self.inputs.clone(),
+            external_limit: self.external_limit.clone(),
+            deadline: self.deadline.map(|limit: Duration| (started, limit)),
+            dry_run: self.dry_run,
+            on_node_start: self.on_node_start.clone(),
+            on_node_complete: self.on_node_complete.clone(),
+            resolver: None,
+        };
+        if let Some(ref on_start) = self.on_node_start {
+            on_start(node_id, &node.op, &resolved_args);
+        }
+        // The error of a node the operation resolved, kept as is when the
+        // operation fails by passing it on
+        let failed_dependency: RefCell<Option<Error>> = RefCell::new(None);
+        let outcome: Result<HashMap<String, Value>> = {
+            // The operation may evaluate other nodes through `Context::resolve`
+            // while this node is still on the visiting path
+            let shared: RefCell<(&mut Engine, &mut Vec<String>)> = RefCell::new((&mut *self, &mut *visiting));
+            let resolve = |reference: &str| -> Result<Value> {
+                let mut guard: RefMut<'_, (&mut Engine, &mut Vec<String>)> = shared.borrow_mut();
+                let (engine, visiting): &mut (&mut Engine, &mut Vec<String>) = &mut guard;
+                engine.execute_node(reference, visiting)
+                    .inspect_err(|e: &Error| *failed_dependency.borrow_mut() = Some(e.clone()))
+            };
+            let ctx: Context = Context { resolver: Some(&resolve), ..base_ctx };
+
+            let attempts: u32 = node.retry.map_or(1, |r: RetryPolicy| r.attempts.max(1));
+            let mut attempt: u32 = 1;
+            loop {
+                let _: Option<Error> = failed_dependency.borrow_mut().take();
+                let started: Instant = Instant::now();
+                let outcome: Result<HashMap<String, Value>> = ctx.run_op(op.as_ref(), &resolved_args);
+                let elapsed: Duration = started.elapsed();
+                shared.borrow_mut().0.metrics.entry(node.op.clone()).or_default().record(elapsed);
+
+                // Only the operation's own failures that may be transient are worth another attempt
+                let passed_on: bool = matches!((&outcome, &*failed_dependency.borrow()), (Err(e), Some(d)) if e == d);
+                let retryable: bool = matches!(outcome, Err(Error::OperationError { .. } | Error::Io { .. })) && !passed_on;
+                match node.retry {
+                    Some(policy) if retryable && attempt < attempts => {
+                        let delay: Duration = policy.delay(attempt);
+                        // No point waiting for an attempt the deadline would cut off
+                        if let Some((started, limit)) = ctx.deadline {
+                            if started.elapsed() + delay >= limit {
+                                break Err(Error::Timeout { node: node_id.to_string(), limit });
+                            }
+                        }
+                        std::thread::sleep(delay);
+                        attempt += 1;
+                    }
+                    _ => break outcome,
+                }
+            }
+        };
+        let outcome: Result<HashMap<String, Value>> = match (outcome, failed_dependency.into_inner()) {
+            (Err(e), Some(dependency)) if e == dependency => Err(e),
+            (outcome, _) => outcome.map_err(|e: Error| -> Error {
                 match e {
                     Error::MissingInput { port, .. } => 
                         Error::MissingInput { node: node_id.to_string(), port },
                     Error::InvalidType { expected, actual, .. } => 
                         Error::InvalidType { node: node_id.to_string(), expected, actual },
                     Error::OperationError { reason, .. } => 
-                        Error::OperationError { node: node_id.to_string(), reason },
+                        Error::OperationError {
+                            node: node_id.to_string(),
+                            reason,
+                            inputs_snapshot: Some(Engine::snapshot_inputs(&resolved_args)),
+                        },
+                    Error::Io { path, reason, .. } =>
+                        Error::Io { node: node_id.to_string(), path, reason },
+                    Error::UnknownOperation { op, .. } =>
+                        Error::UnknownOperation { node: node_id.to_string(), op },
                     _ => e,
                 }
-            })?;
+            }),
+        };
+        if let Some(ref on_complete) = self.on_node_complete {
+            on_complete(node_id, &node.op, &resolved_args, &outcome);
+        }
+        let result: HashMap<String, Value> = outcome?;
 
-        // 6. Type Check Output
-        if let Some(ref declared_type) = node.returns {
-            if let Some(out_val) = result.get("out") {
-                if !declared_type.matches(out_val) {
-                    return Err(Error::InvalidValue {
-                        node: node_id.to_string(),
-                        port: "out".to_string(),
-                        expected_type: declared_type.clone(),
-                        actual_value: format!("{}", out_val),
-                    });
+        // 6. Type Check Outputs
+        if let Some(ref returns) = node.returns {
+            for (port, declared_type) in returns.ports() {
+                if let Some(port_val) = result.get(port) {
+                    if !declared_type.matches(port_val) {
+                        return Err(Error::InvalidValue {
+                            node: node_id.to_string(),
+                            port: port.to_string(),
+                            expected_type: declared_type.clone(),
+                            actual_value: format!("{}", port_val),
+                            path: visiting.clone(),
+                        });
+                    }
                 }
-                let _: Option<SpellType> = self.type_cache.insert(
-                    node_id.to_string(), 
-                    declared_type.clone()
-                );
             }
         }
 
         // 7. Cache Results
-        if let Some(out_val) = result.get("out") {
-            let _: Option<Value> = self.cache.insert(node_id.to_string(), out_val.clone());
+        let result: Arc<HashMap<String, Value>> = Arc::new(result);
+        if node_cacheable && op.is_cacheable(&resolved_args) {
+            self.cache.insert(node_id.to_string(), result.clone());
         }
-        for (port, val) in &result {
-            if port != "out" {
-                let key: String = format!("{}:{}", node_id, port);
-                let _: Option<Value> = self.cache.insert(key, val.clone());
-            }
+
+        Ok(result)
+    }
+
+    /// Falls back to the type of the literal written under `key` when the
+    /// argument has no annotation and literal inference is enabled.
+    fn infer_literal(&self, node: &Node, key: &str, typed_result: Result<TypedValue>) -> Result<TypedValue> {
+        match typed_result {
+            Err(e) if self.infer_literals => node.args.get(key)
+                .and_then(TypedValue::infer_literal)
+                .ok_or(e),
+            other => other,
         }
+    }
+
+    /// Resolves one argument, applying literal inference when enabled.
+    fn resolve_arg(
+        &mut self,
+        node: &Node,
+        node_id: &str,
+        key: &str,
+        typed_result: Result<TypedValue>,
+        visiting: &mut Vec<String>,
+    ) -> Result<Value> {
+        let typed_value: TypedValue = self.infer_literal(node, key, typed_result).map_err(|e: Error| -> Error {
+            match e {
+                Error::MissingTypeAnnotation { port, .. } => 
+                    Error::MissingTypeAnnotation { node: node_id.to_string(), port },
+                Error::UnknownType { port, text, .. } =>
+                    Error::UnknownType { node: node_id.to_string(), port, text },
+                _ => e,
+            }
+        })?;
+        self.resolve_typed_value(&typed_value, node_id, key, visiting)
+    }
 
-        let _: bool = visiting.remove(node_id);
+    /// Renders resolved inputs compactly for error reports, sorted by port
+    /// and truncating long values.
+    fn snapshot_inputs(inputs: &HashMap<String, Value>) -> String {
+        const MAX_VALUE_LEN: usize = 80;
 
-        result.get("out").cloned().ok_or_else(|| Error::OperationError {
-            node: node_id.to_string(),
-            reason: "Operation produced no 'out' output".to_string(),
-        })
+        let mut ports: Vec<&String> = inputs.keys().collect();
+        ports.sort();
+
+        let parts: Vec<String> = ports.into_iter().map(|port: &String| {
+            let mut rendered: String = inputs[port].to_string();
+            if rendered.chars().count() > MAX_VALUE_LEN {
+                rendered = rendered.chars().take(MAX_VALUE_LEN).collect::<String>() + "…";
+            }
+            format!("{}={}", port, rendered)
+        }).collect();
+
+        format!("{{{}}}", parts.join(", "))
     }
 
     /// Resolves a typed value. REQUIRES explicit types.
@@ -136,7 +734,7 @@ impl Engine {
         typed_value: &TypedValue, 
         node_id: &str,
         port_name: &str,
-        visiting: &mut HashSet<String>
+        visiting: &mut Vec<String>
     ) -> Result<Value> {
         // Check if value has explicit type
         let declared_type: &SpellType = typed_value.get_type()
@@ -151,22 +749,21 @@ impl Engine {
                 .ok_or_else(|| Error::OperationError {
                     node: node_id.to_string(),
                     reason: "Invalid reference".to_string(),
+                    inputs_snapshot: None,
                 })?;
             
             // Execute the referenced node
             let resolved: Value = self.execute_node(reference, visiting)?;
             
-            // Type check
+            // Type check against the value just produced: it may differ between
+            // executions of a non-cacheable node, so nothing about it is remembered
             if !declared_type.matches(&resolved) {
-                let actual_type: SpellType = self.type_cache.get(reference)
-                    .cloned()
-                    .unwrap_or(SpellType::Any);
-                
                 return Err(Error::TypeMismatch {
                     node: node_id.to_string(),
                     port: port_name.to_string(),
                     expected: declared_type.clone(),
-                    actual: actual_type,
+                    actual: SpellType::of(&resolved),
+                    path: visiting.clone(),
                 });
             }
             
@@ -179,6 +776,7 @@ impl Engine {
                     port: port_name.to_string(),
                     expected_type: declared_type.clone(),
                     actual_value: format!("{}", literal),
+                    path: visiting.clone(),
                 });
             }
             Ok(literal.clone())
@@ -190,3 +788,987 @@ impl Engine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ops::Operation;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An engine for `graph`, with literal types inferred to keep tests short.
+    fn build(graph: Value) -> Engine {
+        Engine::from_json_str(&graph.to_string()).unwrap().with_literal_inference(true)
+    }
+
+    /// Test operation passing `in` to `out` that counts its calls, optionally
+    /// sleeping first and failing its first `failures` calls.
+    #[derive(Clone, Default)]
+    struct Probe {
+        calls: Arc<AtomicUsize>,
+        delay: Duration,
+        failures: usize,
+        side_effects: bool,
+        uncacheable: bool,
+    }
+
+    impl Probe {
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Operation for Probe {
+        fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            let call: usize = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            std::thread::sleep(self.delay);
+            if call <= self.failures {
+                return Err(Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("failure {}", call),
+                    inputs_snapshot: None,
+                });
+            }
+            let mut out: HashMap<String, Value> = HashMap::new();
+            let _: Option<Value> = out.insert("out".to_string(), inputs.get("in").cloned().unwrap_or(Value::Null));
+            Ok(out)
+        }
+
+        fn has_side_effects(&self) -> bool {
+            self.side_effects
+        }
+
+        fn is_cacheable(&self, _inputs: &HashMap<String, Value>) -> bool {
+            !self.uncacheable
+        }
+    }
+
+    /// Registers `probe` as the `Probe` operation.
+    fn with_probe(engine: Engine, probe: &Probe) -> Engine {
+        let mut ops: Ops = Ops::default();
+        ops.register("Probe", probe.clone());
+        engine.with_ops(ops)
+    }
+
+    #[test]
+    fn non_cacheable_ops_run_for_every_reference() {
+        let graph: Value = json!({
+            "source": { "op": "Probe", "in": 1 },
+            "twice": { "op": "Add", "a": { "ref": "source", "type": "Number" }, "b": { "ref": "source", "type": "Number" } }
+        });
+
+        let fresh: Probe = Probe { uncacheable: true, ..Probe::default() };
+        let mut engine: Engine = with_probe(build(graph.clone()), &fresh);
+        assert_eq!(engine.evaluate("twice").unwrap(), json!(2));
+        assert_eq!(fresh.calls(), 2);
+
+        let cached: Probe = Probe::default();
+        let mut engine: Engine = with_probe(build(graph), &cached);
+        assert_eq!(engine.evaluate("twice").unwrap(), json!(2));
+        assert_eq!(cached.calls(), 1);
+    }
+
+    /// Event log shared with engine callbacks.
+    type Log = std::sync::Arc<std::sync::Mutex<Vec<String>>>;
+
+    #[test]
+    fn progress_fires_once_per_element() {
+        let events: Log = Log::default();
+        let seen: Log = events.clone();
+        let mut engine: Engine = build(json!({
+            "doubled": { "op": "Map", "list": [1, 2, 3], "apply_op": "Mul", "arg": "a", "params": { "literal": { "b": 2 } } }
+        }))
+        .with_progress(move |node: &str, processed: usize, total: usize| {
+            seen.lock().unwrap().push(format!("{} {}/{}", node, processed, total));
+        });
+        assert_eq!(engine.evaluate("doubled").unwrap(), json!([2, 4, 6]));
+        assert_eq!(*events.lock().unwrap(), vec!["doubled 1/3", "doubled 2/3", "doubled 3/3"]);
+    }
+
+    #[test]
+    fn validate_rejects_unknown_ports() {
+        let engine: Engine = build(json!({
+            "sum": { "op": "Add", "a": 1, "b": 2 },
+            "use": { "op": "Add", "a": { "ref": "sum:quotient", "type": "Number" }, "b": 1 }
+        }));
+        match engine.validate() {
+            Err(Error::UnknownPort { node, port }) => assert_eq!((node.as_str(), port.as_str()), ("sum", "quotient")),
+            other => panic!("expected an unknown port, got {:?}", other),
+        }
+
+        let engine: Engine = build(json!({
+            "flag": { "op": "Const", "value": true },
+            "pick": { "op": "Switch", "cond": { "ref": "flag", "type": "Boolean" }, "true": 1, "false": 2 },
+            "use": { "op": "TypeOf", "in": { "ref": "pick:true", "type": "Any" } }
+        }));
+        assert!(engine.validate().is_ok());
+    }
+
+    /// Test operation requiring input `x`, counting its executions.
+    struct NeedsX(Arc<AtomicUsize>);
+
+    impl Operation for NeedsX {
+        fn required_inputs(&self) -> &'static [&'static str] {
+            &["x"]
+        }
+
+        fn execute(&self, _inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            let _: usize = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(HashMap::new())
+        }
+    }
+
+    #[test]
+    fn missing_required_inputs_are_caught_before_execution() {
+        let calls: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let mut ops: Ops = Ops::default();
+        ops.register("NeedsX", NeedsX(calls.clone()));
+        let mut engine: Engine = build(json!({ "n": { "op": "NeedsX", "y": 1 } })).with_ops(ops);
+
+        match engine.evaluate("n") {
+            Err(Error::MissingInput { node, port }) => assert_eq!((node.as_str(), port.as_str()), ("n", "x")),
+            other => panic!("expected a missing input, got {:?}", other),
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn literal_types_are_inferred_only_when_enabled() {
+        let src: &str = r#"{ "sum": { "op": "Add", "a": 2, "b": { "literal": 3 } } }"#;
+        let mut strict: Engine = Engine::from_json_str(src).unwrap();
+        assert!(matches!(strict.evaluate("sum"), Err(Error::MissingTypeAnnotation { .. })));
+
+        let mut lenient: Engine = Engine::from_json_str(src).unwrap().with_literal_inference(true);
+        assert_eq!(lenient.evaluate("sum").unwrap(), json!(5));
+    }
+
+    #[test]
+    fn references_still_need_types_with_inference() {
+        let mut engine: Engine = build(json!({
+            "a": { "op": "Const", "value": 1 },
+            "b": { "op": "Add", "a": { "ref": "a" }, "b": 1 }
+        }));
+        match engine.evaluate("b") {
+            Err(Error::MissingTypeAnnotation { node, port }) => assert_eq!((node.as_str(), port.as_str()), ("b", "a")),
+            other => panic!("expected a missing annotation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn run_stream_yields_every_sink_once() {
+        let mut engine: Engine = build(json!({
+            "x": { "op": "Const", "value": 2 },
+            "double": { "op": "Mul", "a": { "ref": "x", "type": "Number" }, "b": 2 },
+            "square": { "op": "Mul", "a": { "ref": "x", "type": "Number" }, "b": { "ref": "x", "type": "Number" } },
+            "broken": { "op": "Div", "a": 1, "b": 0 }
+        }));
+        let results: Vec<(String, Result<Value>)> = engine.run_stream().collect();
+        let ids: Vec<&str> = results.iter().map(|(id, _): &(String, Result<Value>)| id.as_str()).collect();
+        assert_eq!(ids, vec!["broken", "double", "square"]);
+        assert!(results[0].1.is_err());
+        assert_eq!(results[1].1.as_ref().unwrap(), &json!(4));
+        assert_eq!(results[2].1.as_ref().unwrap(), &json!(4));
+    }
+
+    /// Test operation upper-casing its `in` string.
+    struct Upper;
+
+    impl Operation for Upper {
+        fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            let text: String = inputs.get("in").and_then(Value::as_str).unwrap_or("").to_uppercase();
+            let mut out: HashMap<String, Value> = HashMap::new();
+            let _: Option<Value> = out.insert("out".to_string(), Value::String(text));
+            Ok(out)
+        }
+    }
+
+    #[test]
+    fn registered_custom_ops_execute() {
+        let mut ops: Ops = Ops::default();
+        ops.register("Upper", Upper);
+        let mut engine: Engine = build(json!({
+            "name": { "op": "Const", "value": "spell" },
+            "loud": { "op": "Upper", "in": { "ref": "name", "type": "String" } }
+        })).with_ops(ops);
+        assert_eq!(engine.evaluate("loud").unwrap(), json!("SPELL"));
+        assert!(build(json!({ "loud": { "op": "Upper", "in": "x" } })).validate().is_err());
+    }
+
+    #[test]
+    fn operation_errors_carry_their_inputs() {
+        let mut engine: Engine = build(json!({ "ratio": { "op": "Div", "a": 1, "b": 0 } }));
+        let err: Error = engine.evaluate("ratio").unwrap_err();
+        match &err {
+            Error::OperationError { node, inputs_snapshot, .. } => {
+                assert_eq!(node, "ratio");
+                assert_eq!(inputs_snapshot.as_deref(), Some("{a=1, b=0}"));
+            }
+            other => panic!("expected an operation error, got {:?}", other),
+        }
+        assert!(format!("{:#}", err).ends_with("(inputs: {a=1, b=0})"), "{:#}", err);
+        assert!(!err.to_string().contains("inputs"), "{}", err);
+    }
+
+    #[test]
+    fn input_snapshots_truncate_long_values() {
+        let mut inputs: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = inputs.insert("text".to_string(), json!("x".repeat(200)));
+        let snapshot: String = Engine::snapshot_inputs(&inputs);
+        assert!(snapshot.ends_with("…}"), "{}", snapshot);
+        assert!(snapshot.chars().count() < 100, "{}", snapshot);
+    }
+
+    #[test]
+    fn unchosen_switch_branch_never_runs() {
+        let probe: Probe = Probe::default();
+        let mut engine: Engine = with_probe(build(json!({
+            "expensive": { "op": "Probe", "in": 1 },
+            "cheap": { "op": "Const", "value": 2 },
+            "pick": {
+                "op": "Switch",
+                "cond": true,
+                "true": { "ref": "cheap", "type": "Number" },
+                "false": { "ref": "expensive", "type": "Number" }
+            }
+        })), &probe);
+        assert!(engine.run_collect().is_empty());
+        assert_eq!(engine.evaluate("pick").unwrap(), json!(2));
+        assert_eq!(probe.calls(), 0);
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let probe: Probe = Probe::default();
+        let mut engine: Engine = with_probe(build(json!({
+            "a": { "op": "Probe", "in": 1 },
+            "b": { "op": "Probe", "in": 2 },
+            "c": { "op": "Probe", "in": 3 }
+        })), &probe).with_cache_capacity(2);
+
+        for id in ["a", "b", "c"] {
+            let _: Value = engine.evaluate(id).unwrap();
+        }
+        assert_eq!(probe.calls(), 3);
+        assert_eq!(engine.cache.len(), 2);
+        // `a` was evicted and recomputes; `c` is still cached
+        assert_eq!(engine.evaluate("a").unwrap(), json!(1));
+        assert_eq!(probe.calls(), 4);
+        assert_eq!(engine.evaluate("c").unwrap(), json!(3));
+        assert_eq!(probe.calls(), 4);
+    }
+
+    #[test]
+    fn null_literals_match_unit_optional_and_any() {
+        for declared in ["Unit", "Optional<Number>", "Any"] {
+            let src: String = json!({ "n": { "op": "Coalesce", "a": { "literal": null, "type": declared } } }).to_string();
+            assert_eq!(Engine::from_json_str(&src).unwrap().evaluate("n").unwrap(), Value::Null, "{}", declared);
+        }
+    }
+
+    #[test]
+    fn null_literal_is_not_a_number() {
+        let src: String = json!({ "n": { "op": "Coalesce", "a": { "literal": null, "type": "Number" } } }).to_string();
+        assert!(matches!(Engine::from_json_str(&src).unwrap().evaluate("n"), Err(Error::InvalidValue { .. })));
+    }
+
+    /// Test operation that reports the names of the ports it received.
+    struct Ports;
+
+    impl Operation for Ports {
+        fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+            &[("fn", "apply_op")]
+        }
+
+        fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            let mut names: Vec<&String> = inputs.keys().collect();
+            names.sort();
+            let mut out: HashMap<String, Value> = HashMap::new();
+            let _: Option<Value> = out.insert("out".to_string(), json!(names));
+            Ok(out)
+        }
+    }
+
+    #[test]
+    fn aliased_ports_arrive_under_canonical_names() {
+        let mut ops: Ops = Ops::default();
+        ops.register("Ports", Ports);
+        let mut engine: Engine = build(json!({
+            "p": { "op": "Ports", "fn": { "literal": "Add", "type": "String" }, "list": { "literal": [], "type": "Array<Number>" } }
+        })).with_ops(ops);
+        assert_eq!(engine.evaluate("p").unwrap(), json!(["apply_op", "list"]));
+
+        let mut map: Engine = build(json!({ "m": { "op": "Map", "list": [1, 2], "fn": "Mul", "arg": "a", "params": { "literal": { "b": 3 } } } }));
+        assert_eq!(map.evaluate("m").unwrap(), json!([3, 6]));
+    }
+
+    #[test]
+    fn type_errors_show_the_reference_chain() {
+        let mut engine: Engine = build(json!({
+            "a": { "op": "Const", "value": "text" },
+            "b": { "op": "Coalesce", "a": { "ref": "a", "type": "Number" } },
+            "c": { "op": "Coalesce", "a": { "ref": "b", "type": "Number" } },
+            "d": { "op": "Coalesce", "a": { "ref": "c", "type": "Number" } }
+        }));
+        let err: Error = engine.evaluate("d").unwrap_err();
+        match &err {
+            Error::TypeMismatch { node, path, .. } => {
+                assert_eq!(node, "b");
+                assert_eq!(path, &vec!["d".to_string(), "c".to_string(), "b".to_string()]);
+            }
+            other => panic!("expected a type mismatch, got {:?}", other),
+        }
+        assert!(err.to_string().ends_with("(via d -> c -> b)"), "{}", err);
+        assert!(!build(json!({ "x": { "op": "Coalesce", "a": { "literal": "s", "type": "Number" } } }))
+            .evaluate("x").unwrap_err().to_string().contains("via"));
+    }
+
+    #[test]
+    fn each_declared_port_is_type_checked() {
+        let route = |returns: Value| -> Engine {
+            build(json!({
+                "split": {
+                    "op": "Route", "list": [1, 5], "apply_op": "Gt", "arg": "a",
+                    "params": { "literal": { "b": 2 } }, "returns": returns
+                }
+            }))
+        };
+        assert_eq!(route(json!({ "true": "Array<Number>", "false": "Array<Number>" })).evaluate("split").unwrap(), json!([1, 5]));
+        match route(json!({ "true": "Array<Number>", "false": "Array<String>" })).evaluate("split") {
+            Err(Error::InvalidValue { node, port, .. }) => assert_eq!((node.as_str(), port.as_str()), ("split", "false")),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn metrics_count_calls_per_op() {
+        let mut engine: Engine = build(json!({
+            "a": { "op": "Add", "a": 1, "b": 2 },
+            "b": { "op": "Add", "a": { "ref": "a", "type": "Number" }, "b": 3 },
+            "c": { "op": "Add", "a": { "ref": "b", "type": "Number" }, "b": { "ref": "a", "type": "Number" } },
+            "d": { "op": "Mul", "a": { "ref": "c", "type": "Number" }, "b": 2 }
+        }));
+        assert!(engine.run_collect().is_empty());
+        assert_eq!(engine.metrics()["Add"].calls, 3);
+        assert_eq!(engine.metrics()["Mul"].calls, 1);
+        assert!(engine.metrics()["Add"].mean() <= engine.metrics()["Add"].total);
+    }
+
+    #[test]
+    fn input_nodes_read_injected_values() {
+        let graph: Value = json!({ "who": { "op": "Input", "name": "user" } });
+        let mut engine: Engine = build(graph.clone());
+        engine.set_input("user", json!("ada"));
+        assert_eq!(engine.evaluate("who").unwrap(), json!("ada"));
+
+        match build(graph).evaluate("who") {
+            Err(Error::MissingInput { node, port }) => assert_eq!((node.as_str(), port.as_str()), ("who", "user")),
+            other => panic!("expected a missing input, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn large_values_are_shared_not_copied() {
+        let big: Value = json!({ "literal": (0..10_000).collect::<Vec<i32>>(), "type": "Array<Number>" });
+        let mut engine: Engine = build(json!({
+            "big": { "op": "Const", "value": big },
+            "x": { "op": "Len", "list": { "ref": "big", "type": "Array<Number>" } },
+            "y": { "op": "Len", "list": { "ref": "big", "type": "Array<Number>" } }
+        }));
+        assert!(engine.run_collect().is_empty());
+        let first: Arc<HashMap<String, Value>> = engine.execute_outputs("big", &mut Vec::new()).unwrap();
+        let second: Arc<HashMap<String, Value>> = engine.execute_outputs("big", &mut Vec::new()).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(engine.evaluate("x").unwrap(), json!(10_000));
+    }
+
+    #[test]
+    fn counts_satisfy_integer_returns() {
+        let mut engine: Engine = build(json!({
+            "n": { "op": "Len", "list": { "literal": [1.5, 2.5, 3.5] }, "returns": "Integer" },
+            "best": { "op": "ArgMax", "list": { "literal": [1, 9, 3] }, "returns": "Integer" }
+        }));
+        let n: Value = engine.evaluate("n").unwrap();
+        assert!(n.is_u64(), "{}", n);
+        assert_eq!(n, json!(3));
+        assert!(engine.evaluate("best").unwrap().is_u64());
+    }
+
+    #[test]
+    fn cache_false_nodes_rerun_per_reference() {
+        let fresh: Probe = Probe::default();
+        let cached: Probe = Probe::default();
+        let mut ops: Ops = Ops::default();
+        ops.register("Fresh", fresh.clone());
+        ops.register("Cached", cached.clone());
+        let mut engine: Engine = build(json!({
+            "fresh": { "op": "Fresh", "in": 1, "cache": false },
+            "cached": { "op": "Cached", "in": 2 },
+            "sum": {
+                "op": "Add",
+                "a": { "op": "Add", "a": { "ref": "fresh", "type": "Number" }, "b": { "ref": "cached", "type": "Number" }, "type": "Number" },
+                "b": { "op": "Add", "a": { "ref": "fresh", "type": "Number" }, "b": { "ref": "cached", "type": "Number" }, "type": "Number" }
+            }
+        })).with_ops(ops);
+        assert_eq!(engine.evaluate("sum").unwrap(), json!(6));
+        assert_eq!(fresh.calls(), 2);
+        assert_eq!(cached.calls(), 1);
+    }
+
+    /// Test operation splitting `list` into `head` and `tail`, with no `out`.
+    struct Split;
+
+    impl Operation for Split {
+        fn output_ports(&self) -> &'static [&'static str] {
+            &["head", "tail"]
+        }
+
+        fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            let list: Vec<Value> = inputs["list"].as_array().cloned().unwrap_or_default();
+            let mut out: HashMap<String, Value> = HashMap::new();
+            let _: Option<Value> = out.insert("head".to_string(), list.first().cloned().unwrap_or(Value::Null));
+            let _: Option<Value> = out.insert("tail".to_string(), json!(list.get(1..).unwrap_or_default()));
+            Ok(out)
+        }
+    }
+
+    #[test]
+    fn named_ports_resolve_without_out() {
+        let mut ops: Ops = Ops::default();
+        ops.register("Split", Split);
+        let mut engine: Engine = build(json!({
+            "parts": { "op": "Split", "list": { "literal": [4, 5, 6], "type": "Array<Number>" } },
+            "first": { "op": "Add", "a": { "ref": "parts:head", "type": "Number" }, "b": 0 },
+            "rest": { "op": "Len", "list": { "ref": "parts:tail", "type": "Array<Number>" } }
+        })).with_ops(ops);
+        assert!(engine.validate().is_ok());
+        assert_eq!(engine.evaluate("first").unwrap(), json!(4));
+        assert_eq!(engine.evaluate("rest").unwrap(), json!(2));
+        assert!(engine.evaluate("parts").is_err());
+    }
+
+    /// External test operation recording how many of its calls overlap.
+    #[derive(Clone, Default)]
+    struct Gauge {
+        active: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    impl Operation for Gauge {
+        fn execute(&self, _inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            let now: usize = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            let _: usize = self.peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            let _: usize = self.active.fetch_sub(1, Ordering::SeqCst);
+            let mut out: HashMap<String, Value> = HashMap::new();
+            let _: Option<Value> = out.insert("out".to_string(), Value::Null);
+            Ok(out)
+        }
+
+        fn is_external(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn external_calls_respect_the_concurrency_cap() {
+        let gauge: Gauge = Gauge::default();
+        let limit: Arc<crate::core::limit::ConcurrencyLimit> = Arc::new(crate::core::limit::ConcurrencyLimit::new(2));
+        let workers: Vec<std::thread::JoinHandle<()>> = (0..6).map(|_: i32| {
+            let (gauge, limit): (Gauge, Arc<crate::core::limit::ConcurrencyLimit>) = (gauge.clone(), limit.clone());
+            std::thread::spawn(move || {
+                let mut ops: Ops = Ops::default();
+                ops.register("Gauge", gauge);
+                let mut engine: Engine = build(json!({
+                    "calls": { "op": "Map", "list": [1, 2, 3], "apply_op": "Gauge", "arg": "in" }
+                })).with_ops(ops).with_external_limit(limit);
+                assert_eq!(engine.evaluate("calls").unwrap(), json!([null, null, null]));
+            })
+        }).collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert!(gauge.peak.load(Ordering::SeqCst) <= 2);
+        assert!(gauge.peak.load(Ordering::SeqCst) >= 1);
+    }
+
+    #[test]
+    fn retry_recovers_from_transient_failures() {
+        let probe: Probe = Probe { failures: 2, ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "flaky": { "op": "Probe", "in": 7, "retry": { "attempts": 3, "backoff_ms": 1 } }
+        })), &probe);
+        assert_eq!(engine.evaluate("flaky").unwrap(), json!(7));
+        assert_eq!(probe.calls(), 3);
+    }
+
+    #[test]
+    fn retry_surfaces_the_last_failure() {
+        let probe: Probe = Probe { failures: usize::MAX, ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "flaky": { "op": "Probe", "in": 7, "retry": { "attempts": 3, "backoff_ms": 1 } }
+        })), &probe);
+        assert!(engine.evaluate("flaky").unwrap_err().to_string().contains("failure 3"));
+        assert_eq!(probe.calls(), 3);
+    }
+
+    #[test]
+    fn type_errors_are_not_retried() {
+        let probe: Probe = Probe::default();
+        let mut engine: Engine = with_probe(build(json!({
+            "n": { "op": "Const", "value": "text" },
+            "flaky": { "op": "Probe", "in": { "ref": "n", "type": "Number" }, "retry": { "attempts": 3, "backoff_ms": 1000 } }
+        })), &probe);
+        let begun: Instant = Instant::now();
+        assert!(matches!(engine.evaluate("flaky"), Err(Error::TypeMismatch { .. })));
+        assert!(begun.elapsed() < Duration::from_millis(500));
+        assert_eq!(probe.calls(), 0);
+    }
+
+    /// Test operation emitting a number on odd calls and a string on even ones.
+    struct Flip(Arc<AtomicUsize>);
+
+    impl Operation for Flip {
+        fn execute(&self, _inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            let call: usize = self.0.fetch_add(1, Ordering::SeqCst);
+            let mut out: HashMap<String, Value> = HashMap::new();
+            let value: Value = if call.is_multiple_of(2) { json!(1) } else { json!("one") };
+            let _: Option<Value> = out.insert("out".to_string(), value);
+            Ok(out)
+        }
+
+        fn is_cacheable(&self, _inputs: &HashMap<String, Value>) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn uncacheable_values_are_type_checked_fresh() {
+        let mut ops: Ops = Ops::default();
+        ops.register("Flip", Flip(Arc::default()));
+        let mut engine: Engine = build(json!({
+            "flip": { "op": "Flip" },
+            "use": { "op": "Coalesce", "a": { "ref": "flip", "type": "Number" }, "cache": false }
+        })).with_ops(ops);
+        assert_eq!(engine.evaluate("use").unwrap(), json!(1));
+        assert!(matches!(engine.evaluate("use"), Err(Error::TypeMismatch { .. })));
+        assert_eq!(engine.evaluate("use").unwrap(), json!(1));
+    }
+
+    #[test]
+    fn engines_build_from_inline_json() {
+        let mut engine: Engine = Engine::from_json_str(r#"{
+            "x": { "op": "Const", "value": { "literal": 20, "type": "Number" } },
+            "y": { "op": "Add", "a": { "ref": "x", "type": "Number" }, "b": { "literal": 22, "type": "Number" } }
+        }"#).unwrap();
+        assert_eq!(engine.evaluate("y").unwrap(), json!(42));
+    }
+
+    #[test]
+    fn malformed_json_is_a_parse_error() {
+        match Engine::from_json_str("{\n  \"x\": { \"op\": }\n}") {
+            Err(Error::Parse { line, column, reason }) => {
+                assert_eq!((line, column), (2, 16));
+                assert!(!reason.contains("line"), "{}", reason);
+            }
+            Err(other) => panic!("expected a parse error, got {:?}", other),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn arguments_resolve_in_port_order() {
+        let order = || -> Vec<String> {
+            let events: Log = Log::default();
+            let seen: Log = events.clone();
+            let mut engine: Engine = build(json!({
+                "c": { "op": "Const", "value": 3 },
+                "a": { "op": "Const", "value": 1 },
+                "b": { "op": "Const", "value": 2 },
+                "all": {
+                    "op": "Coalesce",
+                    "z": { "ref": "a", "type": "Number" },
+                    "m": { "ref": "c", "type": "Number" },
+                    "b": { "ref": "b", "type": "Number" }
+                }
+            }))
+            .on_node_start(move |node: &str, _: &str, _: &HashMap<String, Value>| seen.lock().unwrap().push(node.to_string()));
+            let _: Value = engine.evaluate("all").unwrap();
+            let events: Vec<String> = events.lock().unwrap().clone();
+            events
+        };
+        let first: Vec<String> = order();
+        assert_eq!(first, vec!["b", "c", "a", "all"]);
+        assert_eq!(order(), first);
+    }
+
+    #[test]
+    fn result_evaluates_the_output_node() {
+        let graph: Value = json!({
+            "x": { "op": "Const", "value": 6 },
+            "@output": { "op": "Mul", "a": { "ref": "x", "type": "Number" }, "b": 7 }
+        });
+        assert_eq!(build(graph.clone()).result().unwrap(), json!(42));
+        assert_eq!(build(graph).with_output("x").result().unwrap(), json!(6));
+        assert!(matches!(build(json!({ "x": { "op": "Const", "value": 1 } })).result(), Err(Error::NodeNotFound(_))));
+    }
+
+    #[test]
+    fn validate_reports_every_unknown_op_before_running() {
+        let probe: Probe = Probe::default();
+        let engine: Engine = with_probe(build(json!({
+            "ok": { "op": "Probe", "in": 1 },
+            "a": { "op": "Ad", "a": 1, "b": 2 },
+            "b": { "op": "Mull", "a": 1, "b": 2 }
+        })), &probe);
+        match engine.validate() {
+            Err(Error::Multiple(errors)) => {
+                let ops: Vec<String> = errors.iter().map(|e: &Error| e.to_string()).collect();
+                assert_eq!(errors.len(), 2, "{:?}", ops);
+                assert!(matches!(&errors[0], Error::UnknownOperation { node, op } if node == "a" && op == "Ad"));
+                assert!(matches!(&errors[1], Error::UnknownOperation { node, op } if node == "b" && op == "Mull"));
+            }
+            other => panic!("expected both unknown ops, got {:?}", other),
+        }
+        assert_eq!(probe.calls(), 0);
+    }
+
+    /// Runs `graph` with Print writing into a buffer, returning what was printed.
+    fn printed(graph: Value) -> String {
+        let sink: Arc<std::sync::Mutex<Vec<u8>>> = Arc::default();
+        let mut engine: Engine = build(graph).with_print_sink(sink.clone());
+        assert!(engine.run_collect().is_empty());
+        let bytes: Vec<u8> = sink.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn print_writes_labels_to_the_sink() {
+        assert_eq!(printed(json!({ "p": { "op": "Print", "in": 1.5 } })), "OUTPUT: 1.5\n");
+        assert_eq!(printed(json!({ "p": { "op": "Print", "in": 2, "label": "total" } })), "total: 2\n");
+        assert_eq!(printed(json!({ "p": { "op": "Print", "in": "hi", "label": "" } })), "\"hi\"\n");
+    }
+
+    #[test]
+    fn print_writes_json_to_the_sink() {
+        let graph: Value = json!({
+            "p": { "op": "Print", "in": { "literal": { "a": [1, "x"] } }, "label": "", "format": "json" }
+        });
+        assert_eq!(printed(graph), "{\"a\":[1,\"x\"]}\n");
+    }
+
+    #[test]
+    fn print_passes_its_input_through() {
+        let sink: Arc<std::sync::Mutex<Vec<u8>>> = Arc::default();
+        let mut engine: Engine = build(json!({ "p": { "op": "Print", "in": 3 } })).with_print_sink(sink.clone());
+        assert_eq!(engine.evaluate("p").unwrap(), json!(3));
+        assert_eq!(sink.lock().unwrap().as_slice(), b"OUTPUT: 3\n");
+    }
+
+    #[test]
+    fn inline_operations_evaluate_as_anonymous_nodes() {
+        let probe: Probe = Probe::default();
+        let mut engine: Engine = with_probe(build(json!({
+            "x": { "op": "Const", "value": 4 },
+            "y": {
+                "op": "Mul",
+                "a": { "op": "Add", "a": { "ref": "x", "type": "Number" }, "b": 1, "type": "Number" },
+                "b": { "op": "Probe", "in": 2, "type": "Number" }
+            }
+        })), &probe);
+        assert_eq!(engine.evaluate("y").unwrap(), json!(10));
+        assert_eq!(engine.evaluate("y.a").unwrap(), json!(5));
+        assert_eq!(probe.calls(), 1);
+    }
+
+    #[test]
+    fn inline_operations_are_type_checked() {
+        let mut engine: Engine = build(json!({
+            "y": { "op": "Len", "list": { "op": "Add", "a": 1, "b": 2, "type": "Array<Number>" } }
+        }));
+        assert!(matches!(engine.evaluate("y"), Err(Error::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn map_refs_evaluate_the_shared_node_once() {
+        let probe: Probe = Probe::default();
+        let mut engine: Engine = with_probe(build(json!({
+            "step": { "op": "Probe", "in": 3 },
+            "scaled": {
+                "op": "Map", "list": [1, 2, 3, 4], "apply_op": "Mul", "arg": "a",
+                "refs": { "literal": { "b": "step" } }
+            }
+        })), &probe);
+        assert_eq!(engine.evaluate("scaled").unwrap(), json!([3, 6, 9, 12]));
+        assert_eq!(probe.calls(), 1);
+    }
+
+    #[test]
+    fn higher_order_ops_read_refs_through_the_context() {
+        let mut engine: Engine = build(json!({
+            "offset": { "op": "Const", "value": 10 },
+            "list": { "op": "Const", "value": [1, 2, 3] },
+            "shifted": { "op": "Map", "list": { "ref": "list", "type": "Array<Number>" }, "apply_op": "Add", "arg": "a", "refs": { "literal": { "b": "offset" } } },
+            "large": { "op": "Filter", "list": { "ref": "shifted", "type": "Array<Number>" }, "apply_op": "Gt", "arg": "a", "refs": { "literal": { "b": "offset:out" } } },
+            "piped": { "op": "Pipe", "in": 1, "ops": [{ "op": "Mul", "arg": "a", "refs": { "b": "offset" } }] }
+        }));
+        assert_eq!(engine.evaluate("shifted").unwrap(), json!([11, 12, 13]));
+        assert_eq!(engine.evaluate("large").unwrap(), json!([11, 12, 13]));
+        assert_eq!(engine.evaluate("piped").unwrap(), json!(10));
+    }
+
+    #[test]
+    fn refs_conflicting_with_params_are_rejected() {
+        let mut engine: Engine = build(json!({
+            "offset": { "op": "Const", "value": 10 },
+            "shifted": { "op": "Map", "list": [1], "apply_op": "Add", "arg": "a", "params": { "literal": { "b": 1 } }, "refs": { "literal": { "b": "offset" } } }
+        }));
+        let err: String = engine.evaluate("shifted").unwrap_err().to_string();
+        assert!(err.contains("both as a param and as a ref"), "{}", err);
+    }
+
+    #[test]
+    fn errors_of_resolved_refs_keep_their_node() {
+        let mut engine: Engine = build(json!({
+            "bad": { "op": "Div", "a": 1, "b": 0 },
+            "m": { "op": "Map", "list": [1, 2], "apply_op": "Add", "arg": "a", "refs": { "literal": { "b": "bad" } } }
+        }));
+        match engine.evaluate("m") {
+            Err(Error::OperationError { node, reason, inputs_snapshot }) => {
+                assert_eq!((node.as_str(), reason.as_str()), ("bad", "Division by zero"));
+                assert_eq!(inputs_snapshot.as_deref(), Some("{a=1, b=0}"));
+            }
+            other => panic!("expected the dependency's error, got {:?}", other),
+        }
+
+        let mut piped: Engine = build(json!({
+            "bad": { "op": "Div", "a": 1, "b": 0 },
+            "p": { "op": "Pipe", "in": 1, "ops": [{ "op": "Add", "arg": "a", "refs": { "b": "bad" } }] }
+        }));
+        assert!(matches!(piped.evaluate("p"), Err(Error::OperationError { node, .. }) if node == "bad"));
+    }
+
+    #[test]
+    fn failed_refs_are_not_retried_or_reported_as_cycles() {
+        let probe: Probe = Probe { failures: usize::MAX, ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "bad": { "op": "Probe", "in": 1 },
+            "m": {
+                "op": "Map", "list": [1, 2], "apply_op": "Add", "arg": "a",
+                "refs": { "literal": { "b": "bad" } },
+                "retry": { "attempts": 2, "backoff_ms": 1 }
+            }
+        })), &probe);
+        match engine.evaluate("m") {
+            Err(Error::OperationError { node, reason, .. }) => assert_eq!((node.as_str(), reason.as_str()), ("bad", "failure 1")),
+            other => panic!("expected the dependency's error, got {:?}", other),
+        }
+        assert_eq!(probe.calls(), 1);
+    }
+
+    /// Test operation that looks up `bad`, ignores its failure and then fails itself.
+    struct Tolerant;
+
+    impl Operation for Tolerant {
+        fn execute(&self, _inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+            unreachable!("Tolerant needs a context")
+        }
+
+        fn execute_with(&self, _inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+            let _: Result<Value> = ctx.resolve("bad");
+            Err(Error::OperationError { node: "unknown".to_string(), reason: "own failure".to_string(), inputs_snapshot: None })
+        }
+    }
+
+    #[test]
+    fn retries_after_a_tolerated_failure_see_no_cycle() {
+        let mut ops: Ops = Ops::default();
+        ops.register("Tolerant", Tolerant);
+        let mut engine: Engine = build(json!({
+            "bad": { "op": "Div", "a": 1, "b": 0 },
+            "t": { "op": "Tolerant", "retry": { "attempts": 2, "backoff_ms": 1 } }
+        })).with_ops(ops);
+        match engine.evaluate("t") {
+            Err(Error::OperationError { node, reason, .. }) => assert_eq!((node.as_str(), reason.as_str()), ("t", "own failure")),
+            other => panic!("expected the operation's own error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deadline_stops_a_run_of_slow_nodes() {
+        let probe: Probe = Probe { delay: Duration::from_millis(30), ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "a": { "op": "Probe", "in": 1 },
+            "b": { "op": "Probe", "in": { "ref": "a", "type": "Number" } },
+            "c": { "op": "Probe", "in": { "ref": "b", "type": "Number" } },
+            "d": { "op": "Probe", "in": { "ref": "c", "type": "Number" } },
+            "e": { "op": "Probe", "in": { "ref": "d", "type": "Number" } }
+        })), &probe).with_deadline(Duration::from_millis(50));
+        match engine.evaluate("e") {
+            Err(Error::Timeout { .. }) => {}
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(probe.calls() < 5);
+    }
+
+    #[test]
+    fn deadline_restarts_for_each_run() {
+        let mut engine: Engine = build(json!({
+            "now": { "op": "Const", "value": 1, "cache": false }
+        })).with_deadline(Duration::from_millis(50));
+        assert_eq!(engine.evaluate("now").unwrap(), json!(1));
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(engine.evaluate("now").unwrap(), json!(1));
+        assert!(engine.run_collect().is_empty());
+    }
+
+    #[test]
+    fn deadline_applies_inside_while_bodies() {
+        let probe: Probe = Probe { delay: Duration::from_millis(5), ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "loop": {
+                "op": "While",
+                "state": 0,
+                "cond": { "literal": { "out": { "op": "Const", "value": { "literal": true, "type": "Boolean" } } } },
+                "body": { "literal": { "out": { "op": "Probe", "in": { "ref": "state", "type": "Any" } } } }
+            }
+        })), &probe).with_deadline(Duration::from_millis(100));
+
+        let begun: Instant = Instant::now();
+        let err: Error = engine.evaluate("loop").unwrap_err();
+        assert!(matches!(err, Error::Timeout { .. }), "{:?}", err);
+        assert!(begun.elapsed() < Duration::from_secs(2));
+        assert!(probe.calls() < 1000);
+    }
+
+    #[test]
+    fn retry_backoff_stops_at_the_deadline() {
+        let probe: Probe = Probe { failures: usize::MAX, ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "flaky": { "op": "Probe", "in": 1, "retry": { "attempts": 3, "backoff_ms": 60000 } }
+        })), &probe).with_deadline(Duration::from_millis(100));
+
+        let begun: Instant = Instant::now();
+        let err: Error = engine.evaluate("flaky").unwrap_err();
+        assert!(matches!(err, Error::Timeout { ref node, .. } if node == "flaky"), "{:?}", err);
+        assert!(begun.elapsed() < Duration::from_secs(2));
+        assert_eq!(probe.calls(), 1);
+    }
+
+    #[test]
+    fn and_or_skip_the_undecided_operand() {
+        let probe: Probe = Probe::default();
+        let mut engine: Engine = with_probe(build(json!({
+            "expensive": { "op": "Probe", "in": true },
+            "both": { "op": "And", "a": false, "b": { "ref": "expensive", "type": "Boolean" } },
+            "either": { "op": "Or", "a": true, "b": { "ref": "expensive", "type": "Boolean" } },
+            "needed": { "op": "And", "a": true, "b": { "ref": "expensive", "type": "Boolean" } }
+        })), &probe);
+        assert_eq!(engine.evaluate("both").unwrap(), json!(false));
+        assert_eq!(engine.evaluate("either").unwrap(), json!(true));
+        assert_eq!(probe.calls(), 0);
+        assert_eq!(engine.evaluate("needed").unwrap(), json!(true));
+        assert_eq!(probe.calls(), 1);
+    }
+
+    #[test]
+    fn explain_lists_resolved_inputs_without_side_effects() {
+        let probe: Probe = Probe { side_effects: true, ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "x": { "op": "Const", "value": 6 },
+            "product": { "op": "Mul", "a": { "ref": "x", "type": "Number" }, "b": 7 },
+            "ratio": { "op": "Div", "a": { "ref": "product", "type": "Number" }, "b": 0 },
+            "show": { "op": "Probe", "in": { "ref": "ratio", "type": "Number" } },
+            "bad": { "op": "Add", "a": { "ref": "nowhere", "type": "Number" }, "b": 1 }
+        })), &probe);
+        let explained: Vec<Explanation> = engine.explain();
+        let find = |id: &str| -> &Explanation { explained.iter().find(|e: &&Explanation| e.node == id).unwrap() };
+
+        let product: &Explanation = find("product");
+        assert_eq!(product.op, "Mul");
+        let values: Vec<(&str, Value)> = product.inputs.iter()
+            .map(|(port, _, value): &(String, Option<SpellType>, Result<Value>)| (port.as_str(), value.as_ref().unwrap().clone()))
+            .collect();
+        assert_eq!(values, vec![("a", json!(6)), ("b", json!(7))]);
+        assert_eq!(product.inputs[0].1, Some(SpellType::Number));
+        // Resolution errors are reported per port rather than aborting
+        assert!(find("bad").inputs[0].2.is_err());
+        assert!(find("show").inputs[0].2.is_err());
+        assert_eq!(probe.calls(), 0);
+    }
+
+    #[test]
+    fn explain_skips_side_effects_inside_higher_order_ops_and_subgraphs() {
+        let probe: Probe = Probe { side_effects: true, ..Probe::default() };
+        let mut engine: Engine = with_probe(build(json!({
+            "mapped": { "op": "Map", "list": [1, 2], "apply_op": "Probe", "arg": "in" },
+            "count": { "op": "Len", "list": { "ref": "mapped", "type": "Array<Any>" } },
+            "loop": {
+                "op": "While",
+                "state": 0,
+                "cond": { "literal": { "out": { "op": "Const", "value": { "literal": true, "type": "Boolean" } } } },
+                "body": { "literal": { "out": { "op": "Probe", "in": { "ref": "state", "type": "Any" } } } },
+                "max_iterations": 3
+            },
+            "kind": { "op": "TypeOf", "in": { "ref": "loop", "type": "Any" } }
+        })), &probe);
+
+        let explanations: Vec<Explanation> = engine.explain();
+        assert_eq!(probe.calls(), 0);
+        for node in ["count", "kind"] {
+            let explanation: &Explanation = explanations.iter().find(|e: &&Explanation| e.node == node).unwrap();
+            let err: String = explanation.inputs[0].2.as_ref().unwrap_err().to_string();
+            assert!(err.contains("not executed while explaining"), "{}: {}", node, err);
+        }
+
+        assert_eq!(engine.evaluate("count").unwrap(), json!(2));
+        assert_eq!(probe.calls(), 2);
+    }
+
+    #[test]
+    fn hooks_fire_around_each_node_in_order() {
+        let events: Log = Log::default();
+        let (starts, completions): (Log, Log) = (events.clone(), events.clone());
+        let mut engine: Engine = build(json!({
+            "x": { "op": "Const", "value": 2 },
+            "y": { "op": "Div", "a": { "ref": "x", "type": "Number" }, "b": 0 }
+        }))
+        .on_node_start(move |node: &str, op: &str, inputs: &HashMap<String, Value>| {
+            starts.lock().unwrap().push(format!("start {} {} {}", node, op, inputs.len()));
+        })
+        .on_node_complete(move |node: &str, _: &str, _: &HashMap<String, Value>, result: &Result<HashMap<String, Value>>| {
+            completions.lock().unwrap().push(format!("complete {} {}", node, if result.is_ok() { "ok" } else { "err" }));
+        });
+        assert!(engine.evaluate("y").is_err());
+        assert_eq!(*events.lock().unwrap(), vec!["start x Const 1", "complete x ok", "start y Div 2", "complete y err"]);
+    }
+
+    #[test]
+    fn hooks_report_nodes_of_while_bodies() {
+        let events: Log = Log::default();
+        let (starts, completions): (Log, Log) = (events.clone(), events.clone());
+        let mut engine: Engine = build(json!({
+            "loop": {
+                "op": "While",
+                "state": 0,
+                "cond": { "literal": { "out": { "op": "Lt", "a": { "ref": "state", "type": "Number" }, "b": { "literal": 2, "type": "Number" } } } },
+                "body": { "literal": { "out": { "op": "Add", "a": { "ref": "state", "type": "Number" }, "b": { "literal": 1, "type": "Number" } } } }
+            }
+        }))
+        .on_node_start(move |node: &str, op: &str, _: &HashMap<String, Value>| {
+            starts.lock().unwrap().push(format!("start {} {}", node, op));
+        })
+        .on_node_complete(move |node: &str, op: &str, _: &HashMap<String, Value>, _: &Result<HashMap<String, Value>>| {
+            completions.lock().unwrap().push(format!("complete {} {}", node, op));
+        });
+        assert_eq!(engine.evaluate("loop").unwrap(), json!(2));
+
+        let events: Vec<String> = events.lock().unwrap().clone();
+        assert_eq!(events.first().map(String::as_str), Some("start loop While"));
+        assert_eq!(events.last().map(String::as_str), Some("complete loop While"));
+        // Three condition checks and two body runs, each a `state` and an `out` node
+        assert_eq!(events.iter().filter(|e: &&String| e.as_str() == "start out Lt").count(), 3);
+        assert_eq!(events.iter().filter(|e: &&String| e.as_str() == "complete out Add").count(), 2);
+        assert_eq!(events.iter().filter(|e: &&String| e.starts_with("start state")).count(), 5);
+        assert_eq!(events.len(), 22);
+    }
+}
@@ -6,9 +6,11 @@
 //! SPELL error types with explicit type error support.
 
 use std::fmt;
+use std::time::Duration;
 use super::types::SpellType;
 
-#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     /// Node not found in graph
     NodeNotFound(String),
@@ -28,6 +30,8 @@ pub enum Error {
         port: String,
         expected: SpellType, 
         actual: SpellType,
+        /// Reference chain from the evaluated node down to `node`
+        path: Vec<String>,
     },
     
     /// Value doesn't match declared type
@@ -36,6 +40,8 @@ pub enum Error {
         port: String,
         expected_type: SpellType,
         actual_value: String,
+        /// Reference chain from the evaluated node down to `node`
+        path: Vec<String>,
     },
     
     /// Legacy: Invalid type (for backwards compatibility)
@@ -48,17 +54,67 @@ pub enum Error {
     /// Operation-specific error
     OperationError { 
         node: String, 
-        reason: String 
+        reason: String,
+        /// Compact rendering of the resolved inputs, filled in by the engine
+        inputs_snapshot: Option<String>,
     },
     
-    /// Unknown operation
-    UnknownOperation(String),
+    /// Node (or nested call) naming an operation not in the registry
+    UnknownOperation {
+        node: String,
+        op: String,
+    },
+
+    /// Reference to an output port the producing operation does not declare
+    UnknownPort {
+        node: String,
+        port: String,
+    },
+
+    /// Graph source is not valid JSON or does not describe a graph
+    Parse {
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+
+    /// Graph (de)serialization failed
+    Serialization(String),
+
+    /// A file named by a graph's `include` directive could not be merged
+    Include {
+        path: String,
+        reason: String,
+    },
+
+    /// Filesystem access failed
+    Io {
+        node: String,
+        path: String,
+        reason: String,
+    },
+    
+    /// Type annotation that names no known type
+    UnknownType {
+        node: String,
+        port: String,
+        text: String,
+    },
     
     /// Missing type annotation (when explicit types are required)
     MissingTypeAnnotation {
         node: String,
         port: String,
     },
+
+    /// The engine's deadline passed before `node` could run
+    Timeout {
+        node: String,
+        limit: Duration,
+    },
+
+    /// Several independent problems found by one check, e.g. validation
+    Multiple(Vec<Error>),
 }
 
 impl fmt::Display for Error {
@@ -73,30 +129,99 @@ impl fmt::Display for Error {
             Error::MissingInput { node, port } => 
                 write!(f, "Node '{}' missing required input: '{}'", node, port),
             
-            Error::TypeMismatch { node, port, expected, actual } => 
+            Error::TypeMismatch { node, port, expected, actual, path } => {
                 write!(f, "Type mismatch in node '{}' port '{}': expected {}, got {}", 
-                       node, port, expected, actual),
+                       node, port, expected, actual)?;
+                write_path(f, path)
+            }
             
-            Error::InvalidValue { node, port, expected_type, actual_value } =>
+            Error::InvalidValue { node, port, expected_type, actual_value, path } => {
                 write!(f, "Invalid value in node '{}' port '{}': expected type {}, got value '{}'",
-                       node, port, expected_type, actual_value),
+                       node, port, expected_type, actual_value)?;
+                write_path(f, path)
+            }
             
             Error::InvalidType { node, expected, actual } => 
                 write!(f, "Node '{}' expected type '{}', got '{}'", node, expected, actual),
             
-            Error::OperationError { node, reason } => 
-                write!(f, "Operation failed in node '{}': {}", node, reason),
+            // The alternate form (`{:#}`) also shows the inputs the operation received
+            Error::OperationError { node, reason, inputs_snapshot } => {
+                write!(f, "Operation failed in node '{}': {}", node, reason)?;
+                match inputs_snapshot {
+                    Some(snapshot) if f.alternate() => write!(f, " (inputs: {})", snapshot),
+                    _ => Ok(()),
+                }
+            }
+            
+            Error::UnknownOperation { node, op } =>
+                write!(f, "Unknown operation '{}' in node '{}'", op, node),
+
+            Error::UnknownPort { node, port } =>
+                write!(f, "Node '{}' has no output port '{}'", node, port),
+
+            Error::Parse { line, column, reason } =>
+                write!(f, "Parse error at line {} column {}: {}", line, column, reason),
+
+            Error::Serialization(reason) =>
+                write!(f, "Serialization error: {}", reason),
+
+            Error::Include { path, reason } =>
+                write!(f, "Cannot include '{}': {}", path, reason),
+
+            Error::Io { node, path, reason } =>
+                write!(f, "I/O error in node '{}' for path '{}': {}", node, path, reason),
             
-            Error::UnknownOperation(op) => 
-                write!(f, "Unknown operation: '{}'", op),
+            Error::UnknownType { node, port, text } =>
+                write!(f, "Unknown type '{}' in node '{}' port '{}'", text, node, port),
             
             Error::MissingTypeAnnotation { node, port } =>
                 write!(f, "Missing type annotation in node '{}' port '{}' - SPELL requires explicit types", 
                        node, port),
+
+            Error::Timeout { node, limit } =>
+                write!(f, "Deadline of {:?} exceeded before node '{}' could run", limit, node),
+
+            Error::Multiple(errors) => {
+                write!(f, "{} errors:", errors.len())?;
+                for e in errors {
+                    write!(f, "\n  - ")?;
+                    fmt::Display::fmt(e, f)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+impl Error {
+    /// The node an error is attributed to, if it concerns a single node.
+    pub fn node(&self) -> Option<&str> {
+        match self {
+            Error::CycleDetected(node)
+            | Error::MissingInput { node, .. }
+            | Error::TypeMismatch { node, .. }
+            | Error::InvalidValue { node, .. }
+            | Error::InvalidType { node, .. }
+            | Error::OperationError { node, .. }
+            | Error::UnknownOperation { node, .. }
+            | Error::UnknownPort { node, .. }
+            | Error::Io { node, .. }
+            | Error::UnknownType { node, .. }
+            | Error::MissingTypeAnnotation { node, .. }
+            | Error::Timeout { node, .. } => Some(node),
+            Error::NodeNotFound(_) | Error::Parse { .. } | Error::Include { .. } | Error::Serialization(_) | Error::Multiple(_) => None,
+        }
+    }
+}
+
+/// Appends the reference chain that led to a node, when there is more than one hop.
+fn write_path(f: &mut fmt::Formatter<'_>, path: &[String]) -> fmt::Result {
+    if path.len() > 1 {
+        write!(f, " (via {})", path.join(" -> "))?;
+    }
+    Ok(())
+}
+
 impl std::error::Error for Error {}
 
 pub type Result<T> = std::result::Result<T, Error>;
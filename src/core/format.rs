@@ -0,0 +1,86 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL - Value Formatting
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! Rendering of values as text for output.
+
+use serde_json::Value;
+
+/// Controls how numbers are rendered when values are turned into text.
+/// The default renders numbers exactly as `serde_json` does.
+#[derive(Debug, Clone, Default)]
+pub struct NumberFormat {
+    /// Fixed number of decimal places for non-integral numbers.
+    pub decimals: Option<usize>,
+    /// Drops trailing zeros (and a trailing decimal point), so `5.0` renders as `5`.
+    pub trim_trailing_zeros: bool,
+}
+
+impl NumberFormat {
+    /// Renders a single number. Integers always stay integral.
+    pub fn format_number(&self, n: &serde_json::Number) -> String {
+        if n.is_i64() || n.is_u64() {
+            return n.to_string();
+        }
+
+        let Some(f) = n.as_f64() else { return n.to_string() };
+        let mut text: String = match self.decimals {
+            Some(decimals) => format!("{:.*}", decimals, f),
+            None => n.to_string(),
+        };
+
+        if self.trim_trailing_zeros && text.contains('.') && !text.contains(['e', 'E']) {
+            let trimmed_len: usize = text.trim_end_matches('0').trim_end_matches('.').len();
+            text.truncate(trimmed_len);
+        }
+        if text == "-0" {
+            text = "0".to_string();
+        }
+        text
+    }
+
+    /// Renders a value as JSON text, applying the number format throughout.
+    pub fn render(&self, value: &Value) -> String {
+        match value {
+            Value::Number(n) => self.format_number(n),
+            Value::Array(items) => {
+                let parts: Vec<String> = items.iter().map(|item: &Value| self.render(item)).collect();
+                format!("[{}]", parts.join(","))
+            }
+            Value::Object(map) => {
+                let parts: Vec<String> = map.iter()
+                    .map(|(k, v): (&String, &Value)| format!("{}:{}", Value::String(k.clone()), self.render(v)))
+                    .collect();
+                format!("{{{}}}", parts.join(","))
+            }
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(text: &str) -> serde_json::Number {
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn fixed_decimals() {
+        let format: NumberFormat = NumberFormat { decimals: Some(2), trim_trailing_zeros: false };
+        assert_eq!(format.format_number(&number("3.14159")), "3.14");
+        assert_eq!(format.format_number(&number("7")), "7");
+    }
+
+    #[test]
+    fn trailing_zeros_trimmed() {
+        let format: NumberFormat = NumberFormat { decimals: None, trim_trailing_zeros: true };
+        assert_eq!(format.format_number(&number("5.0")), "5");
+        assert_eq!(format.format_number(&number("2.50")), "2.5");
+        assert_eq!(NumberFormat::default().format_number(&number("5.0")), "5.0");
+        let both: NumberFormat = NumberFormat { decimals: Some(3), trim_trailing_zeros: true };
+        assert_eq!(both.render(&serde_json::json!([1.5, 2, {"x": -0.0001}])), "[1.5,2,{\"x\":0}]");
+    }
+}
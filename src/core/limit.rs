@@ -0,0 +1,59 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL - Concurrency Limit
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! Counting semaphore bounding how many external operations run at once.
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// Allows at most `max` holders at a time; further callers block in `acquire`.
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+    max: usize,
+    active: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Held while an operation runs; releases its slot when dropped.
+pub struct Permit<'a> {
+    limit: &'a ConcurrencyLimit,
+}
+
+impl ConcurrencyLimit {
+    /// A limit of `max` concurrent holders. A `max` of zero is treated as one.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max: max.max(1),
+            active: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Blocks until a slot is free, then takes it.
+    pub fn acquire(&self) -> Permit<'_> {
+        let mut active: MutexGuard<'_, usize> = self.lock();
+        while *active >= self.max {
+            active = self.released.wait(active).unwrap_or_else(|e| e.into_inner());
+        }
+        *active += 1;
+        Permit { limit: self }
+    }
+
+    // A panic while holding the lock cannot leave the count inconsistent,
+    // so a poisoned mutex is simply recovered.
+    fn lock(&self) -> MutexGuard<'_, usize> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.limit.lock() -= 1;
+        self.limit.released.notify_one();
+    }
+}
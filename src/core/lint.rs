@@ -0,0 +1,111 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL - Lints
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! Static checks that flag valid but weakly typed graphs.
+
+use std::fmt;
+use std::sync::Arc;
+use super::error::Result;
+use super::ops::{Operation, Ops};
+use super::schema::{Graph, Node, Returns};
+use super::types::{SpellType, TypedValue};
+
+/// A reference annotated `Any` whose producer has a known concrete type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    pub node: String,
+    pub port: String,
+    pub reference: String,
+    pub suggested: SpellType,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node '{}' port '{}' reads '{}' as Any; consider {}",
+               self.node, self.port, self.reference, self.suggested)
+    }
+}
+
+/// Reports every `Any`-typed reference whose source declares a concrete
+/// `returns` type for that port, or whose operation always produces one.
+/// Warnings are sorted by node, then port.
+pub fn lint(graph: &Graph, ops: &Ops) -> Vec<LintWarning> {
+    let mut ids: Vec<&String> = graph.nodes.keys().collect();
+    ids.sort();
+
+    let mut warnings: Vec<LintWarning> = Vec::new();
+    for id in ids {
+        let ports: Vec<(String, TypedValue)> = graph.nodes[id].get_all_typed_args()
+            .into_iter()
+            .filter_map(|(port, typed): (String, Result<TypedValue>)| typed.ok().map(|t: TypedValue| (port, t)))
+            .collect();
+
+        for (port, typed) in ports {
+            let (Some(reference), Some(SpellType::Any)) = (typed.get_reference(), typed.get_type()) else { continue };
+            let (source, source_port): (&str, &str) = match reference.split_once(':') {
+                Some((source, source_port)) if !graph.nodes.contains_key(reference) => (source, source_port),
+                _ => (reference, "out"),
+            };
+            let Some(source_node) = graph.nodes.get(source) else { continue };
+
+            if let Some(suggested) = produced_type(source_node, source_port, ops) {
+                warnings.push(LintWarning {
+                    node: id.clone(),
+                    port,
+                    reference: reference.to_string(),
+                    suggested,
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// The concrete type a node produces on `port`, if known.
+fn produced_type(node: &Node, port: &str, ops: &Ops) -> Option<SpellType> {
+    let declared: Option<SpellType> = node.returns.as_ref()
+        .and_then(|returns: &Returns| returns.for_port(port).cloned());
+    let inferred = || -> Option<SpellType> {
+        if port != "out" {
+            return None;
+        }
+        ops.get(&node.op).and_then(|op: Arc<dyn Operation>| op.output_type())
+    };
+    declared.or_else(inferred).filter(|t: &SpellType| *t != SpellType::Any)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(src: &str) -> Vec<LintWarning> {
+        lint(&Graph::from_json_str(src).unwrap(), &Ops::default())
+    }
+
+    #[test]
+    fn any_reference_to_add_suggests_number() {
+        let found: Vec<LintWarning> = warnings(r#"{
+            "sum": { "op": "Add", "a": { "literal": 1, "type": "Number" }, "b": { "literal": 2, "type": "Number" } },
+            "show": { "op": "Print", "in": { "ref": "sum", "type": "Any" } }
+        }"#);
+        assert_eq!(found, vec![LintWarning {
+            node: "show".to_string(),
+            port: "in".to_string(),
+            reference: "sum".to_string(),
+            suggested: SpellType::Number,
+        }]);
+        assert_eq!(found[0].to_string(), "node 'show' port 'in' reads 'sum' as Any; consider Number");
+    }
+
+    #[test]
+    fn concrete_or_unknown_producers_are_not_flagged() {
+        assert!(warnings(r#"{
+            "sum": { "op": "Add", "a": { "literal": 1, "type": "Number" }, "b": { "literal": 2, "type": "Number" } },
+            "typed": { "op": "Print", "in": { "ref": "sum", "type": "Number" } },
+            "value": { "op": "Const", "value": { "literal": 1, "type": "Number" } },
+            "loose": { "op": "Print", "in": { "ref": "value", "type": "Any" } }
+        }"#).is_empty());
+    }
+}
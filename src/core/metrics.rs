@@ -0,0 +1,32 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL - Operation Metrics
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! Per-operation call counts and timings collected by the engine.
+
+use std::time::Duration;
+
+/// Aggregate timings for one operation name. Durations cover the operation's
+/// own `execute` call, excluding time spent resolving its inputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpMetrics {
+    pub calls: usize,
+    pub total: Duration,
+}
+
+impl OpMetrics {
+    /// Records one call that took `elapsed`.
+    pub fn record(&mut self, elapsed: Duration) {
+        self.calls += 1;
+        self.total += elapsed;
+    }
+
+    /// Average duration per call, or zero if the operation never ran.
+    pub fn mean(&self) -> Duration {
+        u32::try_from(self.calls)
+            .ok()
+            .and_then(|calls: u32| self.total.checked_div(calls))
+            .unwrap_or_default()
+    }
+}
@@ -5,6 +5,13 @@
 
 pub mod types;
 pub mod schema;
+pub mod diff;
 pub mod ops;
 pub mod engine;
+pub mod cache;
 pub mod error;
+pub mod compare;
+pub mod format;
+pub mod metrics;
+pub mod limit;
+pub mod lint;
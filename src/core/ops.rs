@@ -8,9 +8,106 @@
 //! Each operation implements the `Operation` trait.
 //! Operations are stateless and thread-safe.
 
+use base64::Engine as _;
+use serde::Serialize;
 use serde_json::Value;
+use sha2::Digest;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use super::error::{Error, Result};
+use super::types::SpellType;
+use super::schema::{Graph, Node};
+use super::engine::{Engine, NodeCompleteFn, NodeStartFn};
+use super::format::NumberFormat;
+use super::compare::{canonical_number, compare_numbers, compare_values, values_equal};
+use super::limit::{ConcurrencyLimit, Permit};
+
+/// Progress callback invoked with `(node_id, processed, total)`.
+pub type ProgressFn = Arc<dyn Fn(&str, usize, usize) + Send + Sync>;
+
+/// Destination of `Print` output; standard output when none is set.
+pub type PrintSink = Arc<Mutex<dyn Write + Send>>;
+
+/// Callback that evaluates a graph reference (`node` or `node:port`).
+pub type ResolveFn<'a> = &'a dyn Fn(&str) -> Result<Value>;
+
+/// Engine-provided context available to operations during execution.
+#[derive(Clone, Default)]
+pub struct Context<'a> {
+    /// Id of the node being executed.
+    pub node_id: String,
+    /// Directory that filesystem operations are confined to (defaults to the working directory).
+    pub base_dir: Option<PathBuf>,
+    /// Allows network operations to reach plain-HTTP and private/loopback addresses.
+    pub allow_insecure_http: bool,
+    /// Optional callback for per-element progress of array operations.
+    pub progress: Option<ProgressFn>,
+    /// Operation registry used to resolve `apply_op` names and subgraphs.
+    pub ops: Arc<Ops>,
+    /// Number rendering used when values are printed as text.
+    pub number_format: NumberFormat,
+    /// Where `Print` writes, instead of standard output.
+    pub print_sink: Option<PrintSink>,
+    /// Runtime values injected into the engine, read by `Input` nodes.
+    pub inputs: Arc<HashMap<String, Value>>,
+    /// Cap on simultaneously running external operations, if any.
+    pub external_limit: Option<Arc<ConcurrencyLimit>>,
+    /// Start and length of the run's time limit, if any; nested graphs share it.
+    pub deadline: Option<(Instant, Duration)>,
+    /// Set while explaining: `run_op` refuses operations with side effects.
+    pub dry_run: bool,
+    /// Node hooks of the running engine, also called for nodes of nested graphs.
+    pub on_node_start: Option<NodeStartFn>,
+    pub on_node_complete: Option<NodeCompleteFn>,
+    /// Evaluates other nodes of the executing graph; see `resolve`.
+    pub resolver: Option<ResolveFn<'a>>,
+}
+
+impl Context<'_> {
+    /// Evaluates a reference (`node` or `node:port`) in the graph being
+    /// executed, so operations can read nodes they do not take as inputs.
+    /// Results are cached like any other evaluation; referring back to the
+    /// executing node is reported as a cycle.
+    pub fn resolve(&self, reference: &str) -> Result<Value> {
+        match self.resolver {
+            Some(resolve) => resolve(reference),
+            None => Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Cannot resolve '{}' outside of a graph", reference),
+                inputs_snapshot: None,
+            }),
+        }
+    }
+
+    /// Executes `op` with this context, first taking a slot from the external
+    /// concurrency limit if the operation is external. During a dry run,
+    /// operations with side effects are refused instead.
+    pub fn run_op(&self, op: &dyn Operation, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        if self.dry_run && op.has_side_effects() {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: "not executed while explaining: the operation has side effects".to_string(),
+                inputs_snapshot: None,
+            });
+        }
+        let _permit: Option<Permit<'_>> = match self.external_limit {
+            Some(ref limit) if op.is_external() => Some(limit.acquire()),
+            _ => None,
+        };
+        op.execute_with(inputs, self)
+    }
+
+    /// Reports that `processed` of `total` elements are done.
+    pub fn report_progress(&self, processed: usize, total: usize) {
+        if let Some(ref progress) = self.progress {
+            progress(&self.node_id, processed, total);
+        }
+    }
+}
 
 /// Interface for all SPELL operations.
 /// Operations must be stateless, thread-safe, and robust.
@@ -20,31 +117,182 @@ pub trait Operation: Send + Sync {
         &self,
         inputs: &HashMap<String, Value>,
     ) -> Result<HashMap<String, Value>>;
+
+    /// Executes the operation with access to the engine context.
+    /// Operations that need no context keep the default, which delegates to `execute`.
+    fn execute_with(
+        &self,
+        inputs: &HashMap<String, Value>,
+        _ctx: &Context,
+    ) -> Result<HashMap<String, Value>> {
+        self.execute(inputs)
+    }
+
+    /// Input ports that must be supplied. The engine checks these before
+    /// calling `execute`; operations with variadic inputs return an empty slice.
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Alternative port names as `(alias, canonical)` pairs. The engine renames
+    /// aliased inputs to their canonical port before resolving them, so ports can
+    /// be renamed without breaking existing graphs.
+    fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
+    /// Inputs the engine resolves only on demand. After resolving every other
+    /// input, the engine calls `select_lazy_inputs` to learn which are needed.
+    fn lazy_inputs(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Chooses which lazy inputs to resolve, given the eagerly resolved ones.
+    fn select_lazy_inputs(&self, _inputs: &HashMap<String, Value>) -> Vec<&'static str> {
+        self.lazy_inputs().to_vec()
+    }
+
+    /// Output ports this operation may emit. References of the form
+    /// `node:port` are validated against this list, and a bare `node`
+    /// reference requires `out` to be listed. Operations without an `out`
+    /// port are still fine as sinks.
+    fn output_ports(&self) -> &'static [&'static str] {
+        &["out"]
+    }
+
+    /// Type of the `out` port when it is the same for every input, used by
+    /// the linter to suggest tighter annotations than `Any`.
+    fn output_type(&self) -> Option<SpellType> {
+        None
+    }
+
+    /// Type an input port must have, when it is fixed. Higher-order operations
+    /// check their static `params` against it before processing any element.
+    fn input_type(&self, _port: &str) -> Option<SpellType> {
+        None
+    }
+
+    /// Whether this operation calls out to an external service. External
+    /// operations count against the engine's external concurrency limit.
+    fn is_external(&self) -> bool {
+        false
+    }
+
+    /// Whether executing this operation is observable outside the graph, such as
+    /// printing or network access. `Engine::explain` never executes these.
+    fn has_side_effects(&self) -> bool {
+        self.is_external()
+    }
+
+    /// Whether the engine may cache this operation's outputs for these inputs.
+    /// Nondeterministic operations return false so every reference re-executes.
+    fn is_cacheable(&self, _inputs: &HashMap<String, Value>) -> bool {
+        true
+    }
 }
 
 /// Registry for operations.
-pub struct Ops;
+/// `Ops::default()` holds the built-in set; embedders can `register` their own.
+#[derive(Clone)]
+pub struct Ops {
+    ops: HashMap<String, Arc<dyn Operation>>,
+}
 
 impl Ops {
-    /// Creates an operation instance by name.
-    pub fn get(op_name: &str) -> Option<Box<dyn Operation>> {
-        match op_name {
-            "Const" => Some(Box::new(ConstOp)),
-            "Print" => Some(Box::new(PrintOp)),
-            "Add" => Some(Box::new(MathOp::Add)),
-            "Sub" => Some(Box::new(MathOp::Sub)),
-            "Mul" => Some(Box::new(MathOp::Mul)),
-            "Div" => Some(Box::new(MathOp::Div)),
-            "Eq" => Some(Box::new(LogicOp::Eq)),
-            "Gt" => Some(Box::new(LogicOp::Gt)),
-            "Lt" => Some(Box::new(LogicOp::Lt)),
-            "Switch" => Some(Box::new(SwitchOp)),
-            "Map" => Some(Box::new(MapOp)),
-            "Reduce" => Some(Box::new(ReduceOp)),
-            "Len" => Some(Box::new(LenOp)),
-            "Filter" => Some(Box::new(FilterOp)),
-            _ => None,
-        }
+    /// Creates an empty registry with no operations.
+    pub fn empty() -> Self {
+        Self { ops: HashMap::new() }
+    }
+
+    /// Registers `op` under `name`, replacing any existing operation of that name.
+    pub fn register(&mut self, name: &str, op: impl Operation + 'static) {
+        let _: Option<Arc<dyn Operation>> = self.ops.insert(name.to_string(), Arc::new(op));
+    }
+
+    /// Looks up an operation by name.
+    pub fn get(&self, op_name: &str) -> Option<Arc<dyn Operation>> {
+        self.ops.get(op_name).cloned()
+    }
+}
+
+impl Default for Ops {
+    fn default() -> Self {
+        let mut ops: Ops = Ops::empty();
+        ops.register("Const", ConstOp);
+        ops.register("Print", PrintOp);
+        ops.register("Add", MathOp::Add);
+        ops.register("Sub", MathOp::Sub);
+        ops.register("Mul", MathOp::Mul);
+        ops.register("Div", MathOp::Div);
+        ops.register("DivSafe", DivSafeOp);
+        ops.register("Log", UnaryMathOp::Log);
+        ops.register("Log10", UnaryMathOp::Log10);
+        ops.register("Exp", UnaryMathOp::Exp);
+        ops.register("Sin", UnaryMathOp::Sin);
+        ops.register("Cos", UnaryMathOp::Cos);
+        ops.register("Tan", UnaryMathOp::Tan);
+        ops.register("Sqrt", UnaryMathOp::Sqrt);
+        ops.register("Eq", LogicOp::Eq);
+        ops.register("Gt", LogicOp::Gt);
+        ops.register("Lt", LogicOp::Lt);
+        ops.register("And", BoolOp::And);
+        ops.register("Or", BoolOp::Or);
+        ops.register("ApproxEq", ApproxEqOp);
+        ops.register("Switch", SwitchOp);
+        ops.register("Match", MatchOp);
+        ops.register("Map", MapOp);
+        ops.register("Reduce", ReduceOp);
+        ops.register("Scan", ScanOp);
+        ops.register("Pipe", PipeOp);
+        ops.register("Len", LenOp);
+        ops.register("ArgMax", ArgExtremumOp::Max);
+        ops.register("ArgMin", ArgExtremumOp::Min);
+        ops.register("Percentile", PercentileOp::Percentile);
+        ops.register("Median", PercentileOp::Median);
+        ops.register("Normalize", NormalizeOp);
+        ops.register("Validate", ValidateOp);
+        ops.register("Filter", FilterOp);
+        ops.register("MapIf", MapIfOp);
+        ops.register("Route", RouteOp);
+        ops.register("SortBy", SortByOp);
+        ops.register("Random", RandomOp);
+        ops.register("Sample", SampleOp);
+        ops.register("Assert", AssertOp);
+        ops.register("Coalesce", CoalesceOp);
+        ops.register("TypeOf", TypeOfOp);
+        ops.register("Cast", CastOp);
+        ops.register("While", WhileOp);
+        ops.register("Now", NowOp);
+        ops.register("Elapsed", ElapsedOp);
+        ops.register("ParseDate", ParseDateOp);
+        ops.register("FormatDate", FormatDateOp);
+        ops.register("Env", EnvOp);
+        ops.register("Input", InputOp);
+        ops.register("ReadFile", ReadFileOp);
+        ops.register("HttpGet", HttpGetOp);
+        ops.register("Repeat", RepeatOp);
+        ops.register("Chunk", ChunkOp);
+        ops.register("Window", WindowOp);
+        ops.register("Transpose", TransposeOp);
+        ops.register("Interleave", InterleaveOp);
+        ops.register("Union", SetOp::Union);
+        ops.register("Intersect", SetOp::Intersect);
+        ops.register("Difference", SetOp::Difference);
+        ops.register("MakeObject", MakeObjectOp);
+        ops.register("DeepMerge", DeepMergeOp);
+        ops.register("Pick", ProjectOp::Pick);
+        ops.register("Omit", ProjectOp::Omit);
+        ops.register("Render", RenderOp);
+        ops.register("ToCsv", ToCsvOp);
+        ops.register("ParseCsv", ParseCsvOp);
+        ops.register("Base64Encode", Base64Op::Encode);
+        ops.register("Base64Decode", Base64Op::Decode);
+        ops.register("Escape", EscapeOp::Escape);
+        ops.register("Unescape", EscapeOp::Unescape);
+        ops.register("PrettyJson", PrettyJsonOp);
+        ops.register("Hash", HashOp);
+        ops.register("RegexReplace", RegexReplaceOp);
+        ops
     }
 }
 
@@ -66,6 +314,26 @@ fn get_f64(inputs: &HashMap<String, Value>, name: &str) -> Result<f64> {
     })
 }
 
+/// Reads the optional `seed` input, falling back to the system clock.
+fn get_seed(inputs: &HashMap<String, Value>) -> Result<u64> {
+    if inputs.contains_key("seed") {
+        return Ok(get_f64(inputs, "seed")?.to_bits());
+    }
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d: std::time::Duration| d.as_nanos() as u64)
+        .unwrap_or(0))
+}
+
+/// SplitMix64: advances `state` and returns the next well-mixed 64-bit draw.
+fn split_mix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z: u64 = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 fn get_bool(inputs: &HashMap<String, Value>, name: &str) -> Result<bool> {
     let val = get_input(inputs, name)?;
     val.as_bool().ok_or_else(|| Error::InvalidType {
@@ -75,6 +343,55 @@ fn get_bool(inputs: &HashMap<String, Value>, name: &str) -> Result<bool> {
     })
 }
 
+/// Checks static `params` for a higher-order operation's inner `op` against
+/// the types it declares, so a mistyped param fails once up front rather
+/// than on every element.
+fn check_params(op_name: &str, op: &dyn Operation, params: &serde_json::Map<String, Value>) -> Result<()> {
+    for (port, val) in params {
+        if let Some(expected) = op.input_type(port) {
+            if !expected.matches(val) {
+                return Err(Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("param '{}' for {} must be {}, got {} {}",
+                                    port, op_name, expected, SpellType::of(val), val),
+                    inputs_snapshot: None,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds `refs`, an object mapping ports of an inner operation to graph
+/// references (`node` or `node:port`), to its static `params`. Each reference
+/// is evaluated once through [`Context::resolve`], so every call sees the
+/// same value, and the node need not be wired in as an input.
+fn resolve_refs(owner: &str, refs: Option<&Value>, ctx: &Context, params: &mut serde_json::Map<String, Value>) -> Result<()> {
+    let Some(refs) = refs else { return Ok(()) };
+    let refs: &serde_json::Map<String, Value> = refs.as_object().ok_or_else(|| Error::InvalidType {
+        node: owner.to_string(),
+        expected: "object (refs)".to_string(),
+        actual: "non-object".to_string(),
+    })?;
+    for (port, reference) in refs {
+        let reference: &str = reference.as_str().ok_or_else(|| Error::InvalidType {
+            node: owner.to_string(),
+            expected: "string (node reference)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        if params.contains_key(port) {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("'{}' is given both as a param and as a ref", port),
+                inputs_snapshot: None,
+            });
+        }
+        let value: Value = ctx.resolve(reference)?;
+        let _: Option<Value> = params.insert(port.clone(), value);
+    }
+    Ok(())
+}
+
 // ============================================================================
 // OPERATION IMPLEMENTATIONS
 // ============================================================================
@@ -84,6 +401,10 @@ fn get_bool(inputs: &HashMap<String, Value>, name: &str) -> Result<bool> {
 /// Outputs: `out`
 struct ConstOp;
 impl Operation for ConstOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["value"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
         // Const is special: it reads from its own config, which is passed as "value" in inputs
         // (The engine merges config into inputs for simplicity in this architecture)
@@ -95,13 +416,63 @@ impl Operation for ConstOp {
 }
 
 /// Print operation.
-/// Inputs: `in`
+/// Writes `<label>: <value>` to the context's print sink (stdout by
+/// default), or just the value when `label` is empty. The `text` format
+/// applies the engine's number format; `json` prints the value exactly as
+/// JSON, e.g. for log processors.
+/// Inputs: `in`, `label` (optional string, default "OUTPUT"), `format` (optional "text" or "json", default "text")
 /// Outputs: `out` (pass-through)
 struct PrintOp;
 impl Operation for PrintOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
         let val: &Value = get_input(inputs, "in")?;
-        println!("OUTPUT: {}", val);
+        let get_str = |name: &str, default: &'static str| -> Result<String> {
+            match inputs.get(name) {
+                None => Ok(default.to_string()),
+                Some(Value::String(s)) => Ok(s.clone()),
+                Some(_) => Err(Error::InvalidType {
+                    node: "unknown".to_string(),
+                    expected: "string".to_string(),
+                    actual: "non-string".to_string(),
+                }),
+            }
+        };
+        let label: String = get_str("label", "OUTPUT")?;
+        let rendered: String = match get_str("format", "text")?.as_str() {
+            "text" => ctx.number_format.render(val),
+            "json" => val.to_string(),
+            other => return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Unknown Print format '{}', expected \"text\" or \"json\"", other),
+                inputs_snapshot: None,
+            }),
+        };
+        let line: String = if label.is_empty() {
+            format!("{}\n", rendered)
+        } else {
+            format!("{}: {}\n", label, rendered)
+        };
+        let written: std::io::Result<()> = match ctx.print_sink {
+            Some(ref sink) => sink.lock().unwrap_or_else(std::sync::PoisonError::into_inner).write_all(line.as_bytes()),
+            None => std::io::stdout().lock().write_all(line.as_bytes()),
+        };
+        written.map_err(|e: std::io::Error| Error::OperationError {
+            node: "unknown".to_string(),
+            reason: format!("Cannot write Print output: {}", e),
+            inputs_snapshot: None,
+        })?;
         let mut out: HashMap<String, Value> = HashMap::new();
         let _: Option<Value> = out.insert("out".to_string(), val.clone());
         Ok(out)
@@ -109,11 +480,41 @@ impl Operation for PrintOp {
 }
 
 /// Mathematical operations (Add, Sub, Mul, Div).
+/// Add, Sub and Mul of two JSON integers yield an exact integer; on overflow,
+/// or for any other operands, the result is a float.
 /// Inputs: `a`, `b` (numbers)
 /// Outputs: `out`
 enum MathOp { Add, Sub, Mul, Div }
 impl Operation for MathOp {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        match port {
+            "a" | "b" => Some(SpellType::Number),
+            _ => None,
+        }
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Number)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["a", "b"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let ints: Option<(i64, i64)> = get_input(inputs, "a")?.as_i64().zip(get_input(inputs, "b")?.as_i64());
+        let exact: Option<i64> = ints.and_then(|(a, b): (i64, i64)| match self {
+            MathOp::Add => a.checked_add(b),
+            MathOp::Sub => a.checked_sub(b),
+            MathOp::Mul => a.checked_mul(b),
+            MathOp::Div => None,
+        });
+        if let Some(res) = exact {
+            let mut out: HashMap<String, Value> = HashMap::new();
+            let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(res));
+            return Ok(out);
+        }
+
         let a: f64 = get_f64(inputs, "a")?;
         let b: f64 = get_f64(inputs, "b")?;
 
@@ -126,6 +527,7 @@ impl Operation for MathOp {
                     return Err(Error::OperationError {
                         node: "unknown".to_string(),
                         reason: "Division by zero".to_string(),
+                        inputs_snapshot: None,
                     });
                 }
                 a / b
@@ -138,33 +540,138 @@ impl Operation for MathOp {
     }
 }
 
+/// Division that recovers from a zero divisor.
+/// Returns `fallback` (or null when absent) instead of failing when `b` is zero.
+/// Inputs: `a`, `b` (numbers), `fallback` (optional)
+/// Outputs: `out`
+struct DivSafeOp;
+impl Operation for DivSafeOp {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        match port {
+            "a" | "b" => Some(SpellType::Number),
+            _ => None,
+        }
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["a", "b"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let a: f64 = get_f64(inputs, "a")?;
+        let b: f64 = get_f64(inputs, "b")?;
+
+        let res: Value = if b == 0.0_f64 {
+            inputs.get("fallback").cloned().unwrap_or(Value::Null)
+        } else {
+            serde_json::json!(a / b)
+        };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), res);
+        Ok(out)
+    }
+}
+
+/// Unary math functions (Log, Log10, Exp, Sin, Cos, Tan, Sqrt).
+/// Domain errors (log of non-positive, sqrt of negative) and non-finite
+/// results are reported instead of producing NaN/Infinity.
+/// Inputs: `in` (number)
+/// Outputs: `out`
+enum UnaryMathOp { Log, Log10, Exp, Sin, Cos, Tan, Sqrt }
+impl Operation for UnaryMathOp {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        match port {
+            "in" => Some(SpellType::Number),
+            _ => None,
+        }
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Number)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let x: f64 = get_f64(inputs, "in")?;
+
+        let domain_error = |name: &str| -> Error {
+            Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("{} is undefined for {}", name, x),
+                inputs_snapshot: None,
+            }
+        };
+
+        let res: f64 = match self {
+            UnaryMathOp::Log => if x <= 0.0_f64 { return Err(domain_error("Log")); } else { x.ln() },
+            UnaryMathOp::Log10 => if x <= 0.0_f64 { return Err(domain_error("Log10")); } else { x.log10() },
+            UnaryMathOp::Exp => x.exp(),
+            UnaryMathOp::Sin => x.sin(),
+            UnaryMathOp::Cos => x.cos(),
+            UnaryMathOp::Tan => x.tan(),
+            UnaryMathOp::Sqrt => if x < 0.0_f64 { return Err(domain_error("Sqrt")); } else { x.sqrt() },
+        };
+
+        if !res.is_finite() {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Result is not a finite number for input {}", x),
+                inputs_snapshot: None,
+            });
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(res));
+        Ok(out)
+    }
+}
+
 /// Logical comparison operations (Eq, Gt, Lt).
 /// Inputs: `a`, `b`
 /// Outputs: `out` (boolean)
 enum LogicOp { Eq, Gt, Lt }
 impl Operation for LogicOp {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        match (self, port) {
+            (LogicOp::Gt | LogicOp::Lt, "a" | "b") => Some(SpellType::Number),
+            _ => None,
+        }
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Boolean)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["a", "b"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
         let a: &Value = get_input(inputs, "a")?;
         let b: &Value = get_input(inputs, "b")?;
 
-        let res: bool = match (a, b) {
-            (Value::Number(n1), Value::Number(n2)) => {
-                let f1: f64 = n1.as_f64().unwrap_or(0.0_f64); // Fallback to 0.0 if not a standard f64, though as_f64() should handle all numbers
-                let f2: f64 = n2.as_f64().unwrap_or(0.0_f64);
+        let res: bool = match (self, a, b) {
+            (LogicOp::Eq, _, _) => values_equal(a, b),
+            (_, Value::Number(n1), Value::Number(n2)) => {
+                let ord: Ordering = compare_numbers(n1, n2).ok_or_else(|| Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("Cannot compare numbers {} and {}", n1, n2),
+                    inputs_snapshot: None,
+                })?;
                 match self {
-                    LogicOp::Eq => f1 == f2,
-                    LogicOp::Gt => f1 > f2,
-                    LogicOp::Lt => f1 < f2,
+                    LogicOp::Gt => ord == Ordering::Greater,
+                    _ => ord == Ordering::Less,
                 }
             },
-            _ => match self {
-                LogicOp::Eq => a == b,
-                _ => return Err(Error::InvalidType {
-                    node: "unknown".to_string(),
-                    expected: "comparable numbers".to_string(),
-                    actual: "mixed/non-numeric types".to_string(),
-                }),
-            }
+            _ => return Err(Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "comparable numbers".to_string(),
+                actual: "mixed/non-numeric types".to_string(),
+            }),
         };
 
         let mut out: HashMap<String, Value> = HashMap::new();
@@ -173,21 +680,107 @@ impl Operation for LogicOp {
     }
 }
 
+/// Short-circuiting boolean operations (And, Or).
+/// `b` is resolved only when `a` does not already decide the result, so an
+/// expensive or side-effecting operand is skipped when it cannot matter.
+/// Inputs: `a`, `b` (booleans)
+/// Outputs: `out` (boolean)
+enum BoolOp { And, Or }
+impl BoolOp {
+    /// The value of `a` that decides the result on its own.
+    fn decisive(&self) -> bool {
+        match self {
+            BoolOp::And => false,
+            BoolOp::Or => true,
+        }
+    }
+}
+
+impl Operation for BoolOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Boolean)
+    }
+
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        matches!(port, "a" | "b").then_some(SpellType::Boolean)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["a"]
+    }
+
+    fn lazy_inputs(&self) -> &'static [&'static str] {
+        &["b"]
+    }
+
+    fn select_lazy_inputs(&self, inputs: &HashMap<String, Value>) -> Vec<&'static str> {
+        match inputs.get("a").and_then(|v: &Value| v.as_bool()) {
+            Some(a) if a == self.decisive() => Vec::new(),
+            _ => vec!["b"],
+        }
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let a: bool = get_bool(inputs, "a")?;
+        let res: bool = if a == self.decisive() { a } else { get_bool(inputs, "b")? };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Bool(res));
+        Ok(out)
+    }
+}
+
+/// Approximate numeric equality.
+/// Unlike `Eq`, which compares exactly (so `0.1 + 0.2` does not equal `0.3`),
+/// this treats `a` and `b` as equal when `|a - b| <= eps`.
+/// Inputs: `a`, `b` (numbers), `eps` (optional, default 1e-9)
+/// Outputs: `out` (boolean)
+struct ApproxEqOp;
+impl Operation for ApproxEqOp {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        match port {
+            "a" | "b" | "eps" => Some(SpellType::Number),
+            _ => None,
+        }
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Boolean)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["a", "b"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let a: f64 = get_f64(inputs, "a")?;
+        let b: f64 = get_f64(inputs, "b")?;
+        let eps: f64 = if inputs.contains_key("eps") { get_f64(inputs, "eps")? } else { 1e-9_f64 };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!((a - b).abs() <= eps));
+        Ok(out)
+    }
+}
+
 /// Conditional switch operation.
+/// Only the branch selected by `cond` is evaluated.
 /// Inputs: `cond` (bool), `data` (optional), `true` (optional), `false` (optional)
 /// Outputs: `out`, `true` (conditional), `false` (conditional)
 struct SwitchOp;
 impl Operation for SwitchOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["cond"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
         let cond: bool = get_bool(inputs, "cond")?;
+        let branch: &str = if cond { "true" } else { "false" };
         
-        // Mode 1: Branch Selection (if true/false inputs exist)
-        if inputs.contains_key("true") && inputs.contains_key("false") {
-            let val: &Value = if cond {
-                get_input(inputs, "true")?
-            } else {
-                get_input(inputs, "false")?
-            };
+        // Mode 1: Branch Selection (if the chosen branch input exists).
+        // The engine resolves only the chosen branch; see `select_lazy_inputs`.
+        if inputs.contains_key(branch) {
+            let val: &Value = get_input(inputs, branch)?;
             let mut out: HashMap<String, Value> = HashMap::new();
             let _: Option<Value> = out.insert("out".to_string(), val.clone());
             return Ok(out);
@@ -207,6 +800,61 @@ impl Operation for SwitchOp {
         
         Ok(out)
     }
+
+    fn output_ports(&self) -> &'static [&'static str] {
+        &["out", "true", "false"]
+    }
+
+    fn lazy_inputs(&self) -> &'static [&'static str] {
+        &["true", "false"]
+    }
+
+    fn select_lazy_inputs(&self, inputs: &HashMap<String, Value>) -> Vec<&'static str> {
+        match inputs.get("cond").and_then(|v: &Value| v.as_bool()) {
+            Some(true) => vec!["true"],
+            Some(false) => vec!["false"],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// N-way match operation.
+/// Looks up `value` (stringified; whole numbers without a fractional part,
+/// strings without quotes) in `cases` and falls back to `default`.
+/// Inputs: `value`, `cases` (object), `default` (optional)
+/// Outputs: `out`
+struct MatchOp;
+impl Operation for MatchOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["value", "cases"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let val: &Value = get_input(inputs, "value")?;
+        let cases: &serde_json::Map<String, Value> = get_input(inputs, "cases")?.as_object().ok_or_else(|| Error::InvalidType {
+            node: "Match".to_string(),
+            expected: "object (cases)".to_string(),
+            actual: "non-object".to_string(),
+        })?;
+
+        let key: String = match val {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => canonical_number(n),
+            other => other.to_string(),
+        };
+
+        let res: &Value = cases.get(&key)
+            .or_else(|| inputs.get("default"))
+            .ok_or_else(|| Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("No case matches '{}' and no default was given", key),
+                inputs_snapshot: None,
+            })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), res.clone());
+        Ok(out)
+    }
 }
 
 /// Array Map operation.
@@ -216,10 +864,28 @@ impl Operation for SwitchOp {
 /// - `op`: Name of operation to apply (e.g., "Add")
 /// - `arg`: Name of the argument to inject the item into (e.g., "a")
 /// - `params`: Optional static parameters for the operation (e.g., { "b": 1 })
+/// - `refs`: Optional parameters read from other nodes (e.g., { "b": "offset" })
+///
 /// Outputs: `out` (Array)
 struct MapOp;
 impl Operation for MapOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+        &[("fn", "apply_op")]
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "apply_op", "arg"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
         let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
             node: "Map".to_string(),
             expected: "array".to_string(),
@@ -235,7 +901,7 @@ impl Operation for MapOp {
         let item_arg: &str = get_input(inputs, "arg")?.as_str().unwrap_or("in");
         
         // Static parameters to pass to every call
-        let static_params: serde_json::Map<String, Value> = if let Some(params) = inputs.get("params") {
+        let mut static_params: serde_json::Map<String, Value> = if let Some(params) = inputs.get("params") {
             params.as_object().ok_or_else(|| Error::InvalidType {
                 node: "Map".to_string(),
                 expected: "object (params)".to_string(),
@@ -245,7 +911,12 @@ impl Operation for MapOp {
             serde_json::Map::new()
         };
 
-        let op: Box<dyn Operation> = Ops::get(op_name).ok_or_else(|| Error::UnknownOperation(op_name.to_string()))?;
+        let op: Arc<dyn Operation> = ctx.ops.get(op_name).ok_or_else(|| Error::UnknownOperation {
+            node: "unknown".to_string(),
+            op: op_name.to_string(),
+        })?;
+        resolve_refs("Map", inputs.get("refs"), ctx, &mut static_params)?;
+        check_params(op_name, op.as_ref(), &static_params)?;
         
         let mut result_list: Vec<Value> = Vec::new();
         
@@ -260,11 +931,12 @@ impl Operation for MapOp {
             let _: Option<Value> = op_inputs.insert(item_arg.to_string(), item.clone());
             
             // Execute
-            let op_result: HashMap<String, Value> = op.execute(&op_inputs)?;
+            let op_result: HashMap<String, Value> = ctx.run_op(op.as_ref(), &op_inputs)?;
             
             // Collect output (default to "out")
             let out_val: Value = op_result.get("out").unwrap_or(&Value::Null).clone();
             result_list.push(out_val);
+            ctx.report_progress(result_list.len(), list.len());
         }
 
         let mut out: HashMap<String, Value> = HashMap::new();
@@ -281,10 +953,23 @@ impl Operation for MapOp {
 /// - `initial`: Initial accumulator value
 /// - `acc_arg`: Argument name for accumulator (e.g., "a")
 /// - `item_arg`: Argument name for item (e.g., "b")
+///
 /// Outputs: `out` (Value)
 struct ReduceOp;
 impl Operation for ReduceOp {
+    fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+        &[("fn", "apply_op")]
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "apply_op", "initial", "acc_arg", "item_arg"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
         let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
             node: "Reduce".to_string(),
             expected: "array".to_string(),
@@ -301,15 +986,19 @@ impl Operation for ReduceOp {
         let acc_arg: &str = get_input(inputs, "acc_arg")?.as_str().unwrap_or("a");
         let item_arg: &str = get_input(inputs, "item_arg")?.as_str().unwrap_or("b");
 
-        let op: Box<dyn Operation> = Ops::get(op_name).ok_or_else(|| Error::UnknownOperation(op_name.to_string()))?;
+        let op: Arc<dyn Operation> = ctx.ops.get(op_name).ok_or_else(|| Error::UnknownOperation {
+            node: "unknown".to_string(),
+            op: op_name.to_string(),
+        })?;
 
-        for item in list {
+        for (index, item) in list.iter().enumerate() {
             let mut op_inputs: HashMap<String, Value> = HashMap::new();
             let _: Option<Value> = op_inputs.insert(acc_arg.to_string(), acc.clone());
             let _: Option<Value> = op_inputs.insert(item_arg.to_string(), item.clone());
             
-            let op_result: HashMap<String, Value> = op.execute(&op_inputs)?;
+            let op_result: HashMap<String, Value> = ctx.run_op(op.as_ref(), &op_inputs)?;
             acc = op_result.get("out").unwrap_or(&Value::Null).clone();
+            ctx.report_progress(index + 1, list.len());
         }
 
         let mut out: HashMap<String, Value> = HashMap::new();
@@ -318,21 +1007,403 @@ impl Operation for ReduceOp {
     }
 }
 
-/// Array Length operation.
-/// Returns the number of elements in a list.
-/// Inputs: `list`
-/// Outputs: `out` (number)
-struct LenOp;
-impl Operation for LenOp {
+/// Array Scan operation (prefix fold).
+/// Like Reduce, but emits every intermediate accumulator, starting with `initial`.
+/// Inputs: same as Reduce (`list`, `apply_op`, `initial`, `acc_arg`, `item_arg`)
+/// Outputs: `out` (array of `list.len() + 1` accumulator values)
+struct ScanOp;
+impl Operation for ScanOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+        &[("fn", "apply_op")]
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "apply_op", "initial", "acc_arg", "item_arg"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
         let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
-            node: "Len".to_string(),
+            node: "Scan".to_string(),
             expected: "array".to_string(),
             actual: "non-array".to_string(),
         })?;
-
-        let mut out: HashMap<String, Value> = HashMap::new();
-        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(list.len()));
+        
+        let op_name: &str = get_input(inputs, "apply_op")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "Scan".to_string(),
+            expected: "string (op name)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        
+        let mut acc: Value = get_input(inputs, "initial")?.clone();
+        let acc_arg: &str = get_input(inputs, "acc_arg")?.as_str().unwrap_or("a");
+        let item_arg: &str = get_input(inputs, "item_arg")?.as_str().unwrap_or("b");
+
+        let op: Arc<dyn Operation> = ctx.ops.get(op_name).ok_or_else(|| Error::UnknownOperation {
+            node: "unknown".to_string(),
+            op: op_name.to_string(),
+        })?;
+
+        let mut steps: Vec<Value> = Vec::with_capacity(list.len() + 1);
+        steps.push(acc.clone());
+
+        for (index, item) in list.iter().enumerate() {
+            let mut op_inputs: HashMap<String, Value> = HashMap::new();
+            let _: Option<Value> = op_inputs.insert(acc_arg.to_string(), acc.clone());
+            let _: Option<Value> = op_inputs.insert(item_arg.to_string(), item.clone());
+            
+            let op_result: HashMap<String, Value> = ctx.run_op(op.as_ref(), &op_inputs)?;
+            acc = op_result.get("out").unwrap_or(&Value::Null).clone();
+            steps.push(acc.clone());
+            ctx.report_progress(index + 1, list.len());
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(steps));
+        Ok(out)
+    }
+}
+
+/// Pipe operation.
+/// Runs a sequence of operations, feeding each stage's `out` into the next.
+/// Inputs:
+/// - `in`: Initial value
+/// - `ops`: Array of stages `{ "op": name, "arg": input name (default "in"), "params": {...}, "refs": {...} }`
+///
+/// Outputs: `out` (the last stage's `out`)
+struct PipeOp;
+impl Operation for PipeOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in", "ops"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let stages: &Vec<Value> = get_input(inputs, "ops")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "Pipe".to_string(),
+            expected: "array of stages".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let stage_error = |i: usize, reason: String| -> Error {
+            Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Pipe stage {}: {}", i, reason),
+                inputs_snapshot: None,
+            }
+        };
+
+        // Resolve and check every stage before running any of them
+        type Stage<'s> = (&'s str, &'s str, Arc<dyn Operation>, HashMap<String, Value>);
+        let mut resolved: Vec<Stage> = Vec::new();
+        for (i, stage) in stages.iter().enumerate() {
+            let op_name: &str = stage.get("op").and_then(Value::as_str)
+                .ok_or_else(|| stage_error(i, "expected an object with an 'op' name".to_string()))?;
+            let arg: &str = stage.get("arg").and_then(Value::as_str).unwrap_or("in");
+            let op: Arc<dyn Operation> = ctx.ops.get(op_name)
+                .ok_or_else(|| stage_error(i, format!("unknown operation '{}'", op_name)))?;
+
+            let mut params: serde_json::Map<String, Value> = match stage.get("params") {
+                Some(Value::Object(params)) => params.clone(),
+                Some(_) => return Err(stage_error(i, "'params' must be an object".to_string())),
+                None => serde_json::Map::new(),
+            };
+            let in_stage = |e: Error| -> Error {
+                match e {
+                    // Errors of nodes resolved through `refs` already name their node
+                    Error::OperationError { node, reason, .. } if node == "unknown" => stage_error(i, reason),
+                    other => other,
+                }
+            };
+            resolve_refs("Pipe", stage.get("refs"), ctx, &mut params).map_err(in_stage)?;
+            check_params(op_name, op.as_ref(), &params).map_err(in_stage)?;
+            let op_inputs: HashMap<String, Value> = params.into_iter().collect();
+            resolved.push((op_name, arg, op, op_inputs));
+        }
+
+        let mut current: Value = get_input(inputs, "in")?.clone();
+
+        for (i, (op_name, arg, op, mut op_inputs)) in resolved.into_iter().enumerate() {
+            let _: Option<Value> = op_inputs.insert(arg.to_string(), current);
+
+            let mut op_result: HashMap<String, Value> = ctx.run_op(op.as_ref(), &op_inputs)
+                .map_err(|e: Error| -> Error {
+                    let reason: String = match e {
+                        Error::OperationError { reason, .. } => reason,
+                        other => other.to_string(),
+                    };
+                    stage_error(i, format!("{} failed: {}", op_name, reason))
+                })?;
+            current = op_result.remove("out")
+                .ok_or_else(|| stage_error(i, format!("{} produced no 'out' output", op_name)))?;
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), current);
+        Ok(out)
+    }
+}
+
+/// Array Length operation.
+/// Returns the number of elements in a list.
+/// Inputs: `list`
+/// Outputs: `out` (number)
+struct LenOp;
+impl Operation for LenOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Integer)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "Len".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(list.len()));
+        Ok(out)
+    }
+}
+
+/// Array extremum index operations (ArgMax, ArgMin).
+/// Returns the index of the largest (or smallest) number in a list; ties
+/// resolve to the first occurrence. An empty list is an error.
+/// Inputs: `list` (array of numbers)
+/// Outputs: `out` (number)
+enum ArgExtremumOp { Max, Min }
+impl Operation for ArgExtremumOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Integer)
+    }
+
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        (port == "list").then(|| SpellType::Array(Box::new(SpellType::Number)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "unknown".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+        let numbers: Vec<&serde_json::Number> = list.iter()
+            .map(|v: &Value| match v {
+                Value::Number(n) => Ok(n),
+                other => Err(Error::InvalidType {
+                    node: "unknown".to_string(),
+                    expected: "array of numbers".to_string(),
+                    actual: format!("{:?}", other),
+                }),
+            })
+            .collect::<Result<Vec<&serde_json::Number>>>()?;
+
+        let wanted: Ordering = match self {
+            ArgExtremumOp::Max => Ordering::Greater,
+            ArgExtremumOp::Min => Ordering::Less,
+        };
+        let mut best: Option<(usize, &serde_json::Number)> = None;
+        for (index, n) in numbers.into_iter().enumerate() {
+            let better: bool = match best {
+                None => true,
+                Some((_, current)) => compare_numbers(n, current) == Some(wanted),
+            };
+            if better {
+                best = Some((index, n));
+            }
+        }
+
+        let (index, _): (usize, &serde_json::Number) = best.ok_or_else(|| Error::OperationError {
+            node: "unknown".to_string(),
+            reason: "Cannot take the extremum of an empty list".to_string(),
+            inputs_snapshot: None,
+        })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(index));
+        Ok(out)
+    }
+}
+
+/// Percentile operations (Percentile, and Median for `p` = 50).
+/// Sorts the numbers and interpolates linearly between the two closest
+/// ranks. An empty list or a `p` outside 0-100 is an error.
+/// Inputs: `list` (array of numbers), `p` (number, Percentile only)
+/// Outputs: `out` (number)
+enum PercentileOp { Percentile, Median }
+impl Operation for PercentileOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Number)
+    }
+
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        match port {
+            "list" => Some(SpellType::Array(Box::new(SpellType::Number))),
+            "p" => Some(SpellType::Number),
+            _ => None,
+        }
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        match self {
+            PercentileOp::Percentile => &["list", "p"],
+            PercentileOp::Median => &["list"],
+        }
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "unknown".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+        let mut numbers: Vec<f64> = list.iter()
+            .map(|v: &Value| v.as_f64().ok_or_else(|| Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "array of numbers".to_string(),
+                actual: format!("{:?}", v),
+            }))
+            .collect::<Result<Vec<f64>>>()?;
+        let p: f64 = match self {
+            PercentileOp::Percentile => get_f64(inputs, "p")?,
+            PercentileOp::Median => 50.0_f64,
+        };
+
+        let fail = |reason: String| -> Error {
+            Error::OperationError {
+                node: "unknown".to_string(),
+                reason,
+                inputs_snapshot: None,
+            }
+        };
+        if numbers.is_empty() {
+            return Err(fail("Cannot take a percentile of an empty list".to_string()));
+        }
+        if !(0.0_f64..=100.0_f64).contains(&p) {
+            return Err(fail(format!("Percentile must be between 0 and 100, got {}", p)));
+        }
+
+        numbers.sort_by(|a: &f64, b: &f64| a.total_cmp(b));
+        let rank: f64 = p / 100.0_f64 * (numbers.len() - 1) as f64;
+        let (lower, upper): (usize, usize) = (rank.floor() as usize, rank.ceil() as usize);
+        let res: f64 = numbers[lower] + (numbers[upper] - numbers[lower]) * (rank - lower as f64);
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(res));
+        Ok(out)
+    }
+}
+
+/// Min-max Normalize operation.
+/// Scales a list of numbers linearly so its minimum maps to `lo` and its
+/// maximum to `hi`. A list whose elements are all equal maps to `lo`.
+/// Inputs: `list` (array of numbers), `lo` (optional, default 0), `hi` (optional, default 1)
+/// Outputs: `out` (array of numbers)
+struct NormalizeOp;
+impl Operation for NormalizeOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Number)))
+    }
+
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        match port {
+            "list" => Some(SpellType::Array(Box::new(SpellType::Number))),
+            "lo" | "hi" => Some(SpellType::Number),
+            _ => None,
+        }
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "unknown".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+        let numbers: Vec<f64> = list.iter()
+            .map(|v: &Value| v.as_f64().ok_or_else(|| Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "array of numbers".to_string(),
+                actual: format!("{:?}", v),
+            }))
+            .collect::<Result<Vec<f64>>>()?;
+        let lo: f64 = if inputs.contains_key("lo") { get_f64(inputs, "lo")? } else { 0.0_f64 };
+        let hi: f64 = if inputs.contains_key("hi") { get_f64(inputs, "hi")? } else { 1.0_f64 };
+
+        let min: f64 = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+        let max: f64 = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let range: f64 = max - min;
+        let scaled: Vec<Value> = numbers.iter()
+            .map(|x: &f64| {
+                let unit: f64 = if range > 0.0_f64 { (x - min) / range } else { 0.0_f64 };
+                serde_json::json!(lo + unit * (hi - lo))
+            })
+            .collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(scaled));
+        Ok(out)
+    }
+}
+
+/// Finite-number guard for a list.
+/// Passes `list` through unchanged if every element is a finite number, and
+/// otherwise fails naming the first offending index. JSON cannot hold NaN
+/// or infinity, so operations producing them yield null, which is rejected.
+/// Inputs: `list` (array of numbers)
+/// Outputs: `out` (the same array)
+struct ValidateOp;
+impl Operation for ValidateOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Number)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "unknown".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let bad: Option<(usize, &Value)> = list.iter()
+            .enumerate()
+            .find(|(_, v): &(usize, &Value)| !v.as_f64().is_some_and(f64::is_finite));
+        if let Some((index, val)) = bad {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Element {} is not a finite number: {}", index, val),
+                inputs_snapshot: None,
+            });
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(list.clone()));
         Ok(out)
     }
 }
@@ -344,10 +1415,28 @@ impl Operation for LenOp {
 /// - `apply_op`: Name of comparison operation (e.g., "Gt", "Eq")
 /// - `arg`: Argument name for the item (e.g., "a")
 /// - `params`: Static parameters for comparison (e.g., { "b": 10 })
+/// - `refs`: Optional parameters read from other nodes (e.g., { "b": "limit" })
+///
 /// Outputs: `out` (filtered array)
 struct FilterOp;
 impl Operation for FilterOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+        &[("fn", "apply_op")]
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "apply_op", "arg"]
+    }
+
     fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
         let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
             node: "Filter".to_string(),
             expected: "array".to_string(),
@@ -363,7 +1452,7 @@ impl Operation for FilterOp {
         let item_arg: &str = get_input(inputs, "arg")?.as_str().unwrap_or("a");
         
         // Static parameters for the comparison
-        let static_params: serde_json::Map<String, Value> = if let Some(params) = inputs.get("params") {
+        let mut static_params: serde_json::Map<String, Value> = if let Some(params) = inputs.get("params") {
             params.as_object().ok_or_else(|| Error::InvalidType {
                 node: "Filter".to_string(),
                 expected: "object (params)".to_string(),
@@ -373,11 +1462,16 @@ impl Operation for FilterOp {
             serde_json::Map::new()
         };
 
-        let op: Box<dyn Operation> = Ops::get(op_name).ok_or_else(|| Error::UnknownOperation(op_name.to_string()))?;
+        let op: Arc<dyn Operation> = ctx.ops.get(op_name).ok_or_else(|| Error::UnknownOperation {
+            node: "unknown".to_string(),
+            op: op_name.to_string(),
+        })?;
+        resolve_refs("Filter", inputs.get("refs"), ctx, &mut static_params)?;
+        check_params(op_name, op.as_ref(), &static_params)?;
         
         let mut result_list: Vec<Value> = Vec::new();
         
-        for item in list {
+        for (index, item) in list.iter().enumerate() {
             // Construct inputs for this comparison
             let mut op_inputs: HashMap<String, Value> = HashMap::new();
             // 1. Add static params
@@ -388,7 +1482,7 @@ impl Operation for FilterOp {
             let _: Option<Value> = op_inputs.insert(item_arg.to_string(), item.clone());
             
             // Execute comparison
-            let op_result: HashMap<String, Value> = op.execute(&op_inputs)?;
+            let op_result: HashMap<String, Value> = ctx.run_op(op.as_ref(), &op_inputs)?;
             
             // Check if result is true
             let keep: bool = op_result.get("out")
@@ -398,6 +1492,112 @@ impl Operation for FilterOp {
             if keep {
                 result_list.push(item.clone());
             }
+            ctx.report_progress(index + 1, list.len());
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(result_list));
+        Ok(out)
+    }
+}
+
+/// One of MapIf's inner operations, read from its `<prefix>_op`,
+/// `<prefix>_arg` and `<prefix>_params` inputs.
+struct InnerOp {
+    op: Arc<dyn Operation>,
+    arg: String,
+    params: serde_json::Map<String, Value>,
+}
+
+impl InnerOp {
+    fn from_inputs(inputs: &HashMap<String, Value>, ctx: &Context, prefix: &str, default_arg: &str) -> Result<InnerOp> {
+        let op_name: &str = get_input(inputs, &format!("{}_op", prefix))?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "MapIf".to_string(),
+            expected: "string (op name)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        let arg: String = inputs.get(&format!("{}_arg", prefix))
+            .and_then(|v: &Value| v.as_str())
+            .unwrap_or(default_arg)
+            .to_string();
+        let mut params: serde_json::Map<String, Value> = match inputs.get(&format!("{}_params", prefix)) {
+            Some(params) => params.as_object().ok_or_else(|| Error::InvalidType {
+                node: "MapIf".to_string(),
+                expected: "object (params)".to_string(),
+                actual: "non-object".to_string(),
+            })?.clone(),
+            None => serde_json::Map::new(),
+        };
+        let op: Arc<dyn Operation> = ctx.ops.get(op_name).ok_or_else(|| Error::UnknownOperation {
+            node: "unknown".to_string(),
+            op: op_name.to_string(),
+        })?;
+        resolve_refs("MapIf", inputs.get(&format!("{}_refs", prefix)), ctx, &mut params)?;
+        check_params(op_name, op.as_ref(), &params)?;
+        Ok(InnerOp { op, arg, params })
+    }
+
+    /// Runs the operation on `item`, returning its `out` port.
+    fn call(&self, ctx: &Context, item: &Value) -> Result<Option<Value>> {
+        let mut op_inputs: HashMap<String, Value> = self.params.iter()
+            .map(|(k, v): (&String, &Value)| (k.clone(), v.clone()))
+            .collect();
+        let _: Option<Value> = op_inputs.insert(self.arg.clone(), item.clone());
+        Ok(ctx.run_op(self.op.as_ref(), &op_inputs)?.remove("out"))
+    }
+}
+
+/// Conditional Map operation.
+/// Applies `map_op` to the elements for which `predicate_op` returns true
+/// and passes every other element through unchanged.
+/// Inputs:
+/// - `list`: Array of values
+/// - `predicate_op`: Name of the condition operation (e.g., "Gt")
+/// - `predicate_arg`: Argument name for the item in the condition (default "a")
+/// - `predicate_params`: Static parameters for the condition (optional)
+/// - `predicate_refs`: Condition parameters read from other nodes (optional)
+/// - `map_op`: Name of the operation applied to matching items
+/// - `map_arg`: Argument name for the item in the map (default "in")
+/// - `map_params`: Static parameters for the map (optional)
+/// - `map_refs`: Map parameters read from other nodes (optional)
+///
+/// Outputs: `out` (Array, same length as `list`)
+struct MapIfOp;
+impl Operation for MapIfOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "predicate_op", "map_op"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "MapIf".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let predicate: InnerOp = InnerOp::from_inputs(inputs, ctx, "predicate", "a")?;
+        let map: InnerOp = InnerOp::from_inputs(inputs, ctx, "map", "in")?;
+
+        let mut result_list: Vec<Value> = Vec::new();
+        for item in list {
+            let matched: bool = predicate.call(ctx, item)?
+                .and_then(|v: Value| v.as_bool())
+                .unwrap_or(false);
+            let value: Value = if matched {
+                map.call(ctx, item)?.unwrap_or(Value::Null)
+            } else {
+                item.clone()
+            };
+            result_list.push(value);
+            ctx.report_progress(result_list.len(), list.len());
         }
 
         let mut out: HashMap<String, Value> = HashMap::new();
@@ -405,3 +1605,2853 @@ impl Operation for FilterOp {
         Ok(out)
     }
 }
+
+/// Array Route operation (vectorized Switch).
+/// Splits a list by a predicate, keeping both halves.
+/// Inputs: same as Filter (`list`, `apply_op`, `arg`, `params`, `refs`)
+/// Outputs: `true` (elements where the predicate held), `false` (the rest),
+/// `out` (the list, passed through like Switch)
+struct RouteOp;
+impl Operation for RouteOp {
+    fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+        &[("fn", "apply_op")]
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "apply_op", "arg"]
+    }
+
+    fn output_ports(&self) -> &'static [&'static str] {
+        &["out", "true", "false"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "Route".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+        
+        let op_name: &str = get_input(inputs, "apply_op")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "Route".to_string(),
+            expected: "string (op name)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        
+        let item_arg: &str = get_input(inputs, "arg")?.as_str().unwrap_or("a");
+        
+        let mut static_params: serde_json::Map<String, Value> = if let Some(params) = inputs.get("params") {
+            params.as_object().ok_or_else(|| Error::InvalidType {
+                node: "Route".to_string(),
+                expected: "object (params)".to_string(),
+                actual: "non-object".to_string(),
+            })?.clone()
+        } else {
+            serde_json::Map::new()
+        };
+
+        let op: Arc<dyn Operation> = ctx.ops.get(op_name).ok_or_else(|| Error::UnknownOperation {
+            node: "unknown".to_string(),
+            op: op_name.to_string(),
+        })?;
+        resolve_refs("Route", inputs.get("refs"), ctx, &mut static_params)?;
+        check_params(op_name, op.as_ref(), &static_params)?;
+        
+        let mut matched: Vec<Value> = Vec::new();
+        let mut rest: Vec<Value> = Vec::new();
+        
+        for (index, item) in list.iter().enumerate() {
+            let mut op_inputs: HashMap<String, Value> = HashMap::new();
+            for (k, v) in &static_params {
+                let _: Option<Value> = op_inputs.insert(k.clone(), v.clone());
+            }
+            let _: Option<Value> = op_inputs.insert(item_arg.to_string(), item.clone());
+            
+            let op_result: HashMap<String, Value> = ctx.run_op(op.as_ref(), &op_inputs)?;
+            let keep: bool = op_result.get("out")
+                .and_then(|v: &Value| -> Option<bool> { v.as_bool() })
+                .unwrap_or(false);
+            
+            if keep {
+                matched.push(item.clone());
+            } else {
+                rest.push(item.clone());
+            }
+            ctx.report_progress(index + 1, list.len());
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("true".to_string(), Value::Array(matched));
+        let _: Option<Value> = out.insert("false".to_string(), Value::Array(rest));
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(list.clone()));
+        Ok(out)
+    }
+}
+
+/// Array Sort-By operation.
+/// Sorts a list by a key extracted from each element.
+/// Inputs:
+/// - `list`: Array of values
+/// - `key`: Field name to sort objects by (used when `apply_op` is absent)
+/// - `apply_op`: Name of extractor operation (e.g., "Mul")
+/// - `arg`: Argument name for the item (e.g., "a")
+/// - `params`: Optional static parameters for the extractor
+/// - `refs`: Optional extractor parameters read from other nodes
+/// - `desc`: Optional boolean, sorts descending when true
+///
+/// Outputs: `out` (sorted array)
+struct SortByOp;
+impl Operation for SortByOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn input_aliases(&self) -> &'static [(&'static str, &'static str)] {
+        &[("fn", "apply_op")]
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "SortBy".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let desc: bool = if inputs.contains_key("desc") { get_bool(inputs, "desc")? } else { false };
+
+        // Extract the sort key for every element
+        let mut keyed: Vec<(Value, Value)> = Vec::with_capacity(list.len());
+
+        if let Some(op_val) = inputs.get("apply_op") {
+            let op_name: &str = op_val.as_str().ok_or_else(|| Error::InvalidType {
+                node: "SortBy".to_string(),
+                expected: "string (op name)".to_string(),
+                actual: "non-string".to_string(),
+            })?;
+
+            let item_arg: &str = inputs.get("arg").and_then(|v: &Value| v.as_str()).unwrap_or("in");
+
+            let mut static_params: serde_json::Map<String, Value> = if let Some(params) = inputs.get("params") {
+                params.as_object().ok_or_else(|| Error::InvalidType {
+                    node: "SortBy".to_string(),
+                    expected: "object (params)".to_string(),
+                    actual: "non-object".to_string(),
+                })?.clone()
+            } else {
+                serde_json::Map::new()
+            };
+
+            let op: Arc<dyn Operation> = ctx.ops.get(op_name).ok_or_else(|| Error::UnknownOperation {
+                node: "unknown".to_string(),
+                op: op_name.to_string(),
+            })?;
+            resolve_refs("SortBy", inputs.get("refs"), ctx, &mut static_params)?;
+            check_params(op_name, op.as_ref(), &static_params)?;
+
+            for item in list {
+                let mut op_inputs: HashMap<String, Value> = HashMap::new();
+                for (k, v) in &static_params {
+                    let _: Option<Value> = op_inputs.insert(k.clone(), v.clone());
+                }
+                let _: Option<Value> = op_inputs.insert(item_arg.to_string(), item.clone());
+
+                let op_result: HashMap<String, Value> = ctx.run_op(op.as_ref(), &op_inputs)?;
+                let key_val: Value = op_result.get("out").unwrap_or(&Value::Null).clone();
+                keyed.push((key_val, item.clone()));
+            }
+        } else {
+            let key: &str = get_input(inputs, "key")?.as_str().ok_or_else(|| Error::InvalidType {
+                node: "SortBy".to_string(),
+                expected: "string (field name)".to_string(),
+                actual: "non-string".to_string(),
+            })?;
+
+            for item in list {
+                let key_val: Value = item.get(key).cloned().unwrap_or(Value::Null);
+                keyed.push((key_val, item.clone()));
+            }
+        }
+
+        // Every extracted key must be comparable with the others
+        for pair in keyed.windows(2) {
+            if compare_values(&pair[0].0, &pair[1].0).is_none() {
+                return Err(Error::InvalidType {
+                    node: "SortBy".to_string(),
+                    expected: "comparable sort keys (numbers, strings or booleans)".to_string(),
+                    actual: format!("{} and {}", pair[0].0, pair[1].0),
+                });
+            }
+        }
+        if let [(only, _)] = keyed.as_slice() {
+            if compare_values(only, only).is_none() {
+                return Err(Error::InvalidType {
+                    node: "SortBy".to_string(),
+                    expected: "comparable sort key (number, string or boolean)".to_string(),
+                    actual: format!("{}", only),
+                });
+            }
+        }
+
+        keyed.sort_by(|x: &(Value, Value), y: &(Value, Value)| -> Ordering {
+            let ord: Ordering = compare_values(&x.0, &y.0).unwrap_or(Ordering::Equal);
+            if desc { ord.reverse() } else { ord }
+        });
+
+        let sorted: Vec<Value> = keyed.into_iter().map(|(_, item): (Value, Value)| item).collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(sorted));
+        Ok(out)
+    }
+}
+
+/// Pseudo-random number operation.
+/// Produces a number in `[min, max)` derived deterministically from `seed`,
+/// so identical graphs reproduce identical values.
+/// Without a `seed` the value is drawn from the system clock and is
+/// nondeterministic.
+/// Inputs: `min` (optional, default 0), `max` (optional, default 1), `seed` (optional)
+/// Outputs: `out` (number)
+struct RandomOp;
+impl Operation for RandomOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Number)
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let min: f64 = if inputs.contains_key("min") { get_f64(inputs, "min")? } else { 0.0_f64 };
+        let max: f64 = if inputs.contains_key("max") { get_f64(inputs, "max")? } else { 1.0_f64 };
+
+        if max < min {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Random range is empty: min {} > max {}", min, max),
+                inputs_snapshot: None,
+            });
+        }
+
+        let mut state: u64 = get_seed(inputs)?;
+        let z: u64 = split_mix64(&mut state);
+
+        // Top 53 bits give a uniform float in [0, 1)
+        let unit: f64 = (z >> 11) as f64 / (1_u64 << 53) as f64;
+        let res: f64 = min + unit * (max - min);
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(res));
+        Ok(out)
+    }
+
+    fn is_cacheable(&self, _inputs: &HashMap<String, Value>) -> bool {
+        false
+    }
+}
+
+/// Random Sample operation.
+/// Draws `n` elements without replacement, deterministically for a given
+/// `seed`. If `n` exceeds the length the whole list is returned, shuffled
+/// only when a `seed` is given.
+/// Inputs: `list`, `n` (non-negative number), `seed` (optional)
+/// Outputs: `out` (array)
+struct SampleOp;
+impl Operation for SampleOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "n"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let mut list: Vec<Value> = get_input(inputs, "list")?.as_array().cloned().ok_or_else(|| Error::InvalidType {
+            node: "Sample".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let n: f64 = get_f64(inputs, "n")?;
+        if n < 0.0_f64 || n.fract() != 0.0_f64 {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Sample size must be a non-negative integer, got {}", n),
+                inputs_snapshot: None,
+            });
+        }
+        let n: usize = (n as usize).min(list.len());
+
+        if n < list.len() || inputs.contains_key("seed") {
+            // Partial Fisher-Yates: the first `n` slots end up a uniform sample
+            let mut state: u64 = get_seed(inputs)?;
+            for i in 0..n {
+                let j: usize = i + (split_mix64(&mut state) % (list.len() - i) as u64) as usize;
+                list.swap(i, j);
+            }
+            list.truncate(n);
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(list));
+        Ok(out)
+    }
+
+    fn is_cacheable(&self, inputs: &HashMap<String, Value>) -> bool {
+        inputs.contains_key("seed")
+    }
+}
+
+/// Assertion operation.
+/// Aborts the node with an error when `cond` is false.
+/// Inputs: `cond` (bool), `message` (optional string)
+/// Outputs: `out` (pass-through of `cond`)
+struct AssertOp;
+impl Operation for AssertOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Boolean)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["cond"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let cond: bool = get_bool(inputs, "cond")?;
+
+        if !cond {
+            let message: String = inputs.get("message")
+                .and_then(|v: &Value| -> Option<&str> { v.as_str() })
+                .unwrap_or("Assertion failed")
+                .to_string();
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: message,
+                inputs_snapshot: None,
+            });
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(cond));
+        Ok(out)
+    }
+}
+
+/// Null-coalescing operation.
+/// Returns the first non-null value, or null if every candidate is null.
+/// Inputs: `values` (array), or ordered ports `a`, `b`, `c`, ... (alphabetical)
+/// Outputs: `out`
+struct CoalesceOp;
+impl Operation for CoalesceOp {
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let candidates: Vec<&Value> = if let Some(values) = inputs.get("values") {
+            values.as_array().ok_or_else(|| Error::InvalidType {
+                node: "Coalesce".to_string(),
+                expected: "array".to_string(),
+                actual: "non-array".to_string(),
+            })?.iter().collect()
+        } else {
+            let mut ports: Vec<&String> = inputs.keys().collect();
+            ports.sort();
+            ports.into_iter().map(|port: &String| -> &Value { &inputs[port] }).collect()
+        };
+
+        let res: Value = candidates.into_iter()
+            .find(|v: &&Value| !v.is_null())
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), res);
+        Ok(out)
+    }
+}
+
+/// Runtime type introspection operation.
+/// Inputs: `in`
+/// Outputs: `out` (string, e.g. "Number", "Array<String>", "Unit" for null)
+struct TypeOfOp;
+impl Operation for TypeOfOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let val: &Value = get_input(inputs, "in")?;
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(SpellType::of(val).to_string()));
+        Ok(out)
+    }
+}
+
+/// Checked type coercion operation.
+/// Supported coercions: String <-> Number, Number/String -> Boolean,
+/// Boolean -> Number (0/1), anything scalar -> String, element-wise
+/// casts into `Array<T>`, and null passing through `Optional<T>`.
+/// Inputs: `in`, `to` (type string, e.g. "Number")
+/// Outputs: `out`
+struct CastOp;
+
+impl CastOp {
+    fn cast(val: &Value, to: &SpellType) -> Result<Value> {
+        let undefined = || -> Error {
+            Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Cannot cast {} value {} to {}", SpellType::of(val), val, to),
+                inputs_snapshot: None,
+            }
+        };
+
+        match (to, val) {
+            (SpellType::Any, _) => Ok(val.clone()),
+            (_, _) if to.matches(val) => Ok(val.clone()),
+
+            (SpellType::Number, Value::String(s)) => {
+                let n: f64 = s.trim().parse::<f64>().map_err(|_| undefined())?;
+                serde_json::Number::from_f64(n).map(Value::Number).ok_or_else(undefined)
+            }
+            (SpellType::Number, Value::Bool(b)) => Ok(serde_json::json!(if *b { 1 } else { 0 })),
+
+            (SpellType::Integer, Value::Number(_) | Value::String(_) | Value::Bool(_)) => {
+                let n: Value = CastOp::cast(val, &SpellType::Number)?;
+                match n.as_f64() {
+                    Some(f) if f.fract() == 0.0_f64 && f.abs() < 9.007_199_254_740_992e15_f64 => Ok(serde_json::json!(f as i64)),
+                    _ => Err(undefined()),
+                }
+            }
+
+            (SpellType::String, Value::Number(n)) => Ok(Value::String(n.to_string())),
+            (SpellType::String, Value::Bool(b)) => Ok(Value::String(b.to_string())),
+
+            (SpellType::Boolean, Value::Number(n)) => n.as_f64()
+                .map(|f: f64| Value::Bool(f != 0.0_f64))
+                .ok_or_else(undefined),
+            (SpellType::Boolean, Value::String(s)) => match s.trim() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(undefined()),
+            },
+
+            (SpellType::Optional(inner), _) => CastOp::cast(val, inner),
+
+            (SpellType::SizedArray(inner, len), Value::Array(items)) if items.len() == *len => {
+                CastOp::cast(val, &SpellType::Array(inner.clone()))
+            }
+            (SpellType::Array(inner), Value::Array(items)) => {
+                let cast_items: Vec<Value> = items.iter()
+                    .map(|item: &Value| CastOp::cast(item, inner))
+                    .collect::<Result<Vec<Value>>>()?;
+                Ok(Value::Array(cast_items))
+            }
+
+            _ => Err(undefined()),
+        }
+    }
+}
+
+impl Operation for CastOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in", "to"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let val: &Value = get_input(inputs, "in")?;
+        let to_str: &str = get_input(inputs, "to")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "Cast".to_string(),
+            expected: "string (type name)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        let to: SpellType = SpellType::parse(to_str).map_err(|reason: String| Error::OperationError {
+            node: "unknown".to_string(),
+            reason,
+            inputs_snapshot: None,
+        })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), CastOp::cast(val, &to)?);
+        Ok(out)
+    }
+}
+
+/// Bounded loop operation over subgraphs.
+/// `cond` and `body` are graph literals. Before each evaluation the current
+/// state is injected as a `Const` node named `state`; each subgraph's result
+/// is the value of its `out` node.
+/// Inputs:
+/// - `state`: Initial state value
+/// - `cond`: Subgraph producing a boolean; the loop runs while it is true
+/// - `body`: Subgraph producing the next state
+/// - `max_iterations`: Optional iteration cap (default 1000)
+///
+/// Outputs: `out` (final state)
+struct WhileOp;
+
+impl WhileOp {
+    const DEFAULT_MAX_ITERATIONS: u64 = 1000;
+
+    fn parse_subgraph(inputs: &HashMap<String, Value>, name: &str) -> Result<Graph> {
+        let val: &Value = get_input(inputs, name)?;
+        serde_json::from_value::<Graph>(val.clone()).map_err(|e: serde_json::Error| Error::InvalidType {
+            node: "While".to_string(),
+            expected: format!("graph ({})", name),
+            actual: e.to_string(),
+        })
+    }
+
+    fn run_subgraph(graph: &Graph, state: &Value, ctx: &Context) -> Result<Value> {
+        let mut graph: Graph = graph.clone();
+        let mut args: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = args.insert(
+            "value".to_string(),
+            serde_json::json!({ "literal": state, "type": "Any" }),
+        );
+        let _: Option<Node> = graph.nodes.insert("state".to_string(), Node {
+            op: "Const".to_string(),
+            desc: None,
+            returns: None,
+            cache: None,
+            retry: None,
+            args,
+        });
+
+        Engine::from_context(graph, ctx).evaluate("out")
+    }
+}
+
+impl Operation for WhileOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["state", "cond", "body"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let cond: Graph = WhileOp::parse_subgraph(inputs, "cond")?;
+        let body: Graph = WhileOp::parse_subgraph(inputs, "body")?;
+        let mut state: Value = get_input(inputs, "state")?.clone();
+        let max_iterations: u64 = if inputs.contains_key("max_iterations") {
+            get_f64(inputs, "max_iterations")? as u64
+        } else {
+            WhileOp::DEFAULT_MAX_ITERATIONS
+        };
+
+        let mut iterations: u64 = 0;
+        loop {
+            let keep_going: bool = WhileOp::run_subgraph(&cond, &state, ctx)?
+                .as_bool()
+                .ok_or_else(|| Error::InvalidType {
+                    node: "While".to_string(),
+                    expected: "boolean (cond result)".to_string(),
+                    actual: "non-boolean".to_string(),
+                })?;
+            if !keep_going {
+                break;
+            }
+
+            if iterations >= max_iterations {
+                return Err(Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("While exceeded max_iterations ({})", max_iterations),
+                    inputs_snapshot: None,
+                });
+            }
+
+            state = WhileOp::run_subgraph(&body, &state, ctx)?;
+            iterations += 1;
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), state);
+        Ok(out)
+    }
+}
+
+/// Current time operation.
+/// Never cached, so each reference reads the clock again.
+/// Inputs: none
+/// Outputs: `out` (Unix timestamp in seconds, fractional)
+struct NowOp;
+impl Operation for NowOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Number)
+    }
+
+    fn execute(&self, _inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let now: f64 = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e: std::time::SystemTimeError| Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("System clock is before the Unix epoch: {}", e),
+                inputs_snapshot: None,
+            })?
+            .as_secs_f64();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(now));
+        Ok(out)
+    }
+
+    fn is_cacheable(&self, _inputs: &HashMap<String, Value>) -> bool {
+        false
+    }
+}
+
+/// Duration between two timestamps.
+/// Inputs: `start`, `end` (Unix timestamps in seconds)
+/// Outputs: `out` (seconds, `end - start`)
+struct ElapsedOp;
+impl Operation for ElapsedOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Number)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["start", "end"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let start: f64 = get_f64(inputs, "start")?;
+        let end: f64 = get_f64(inputs, "end")?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), serde_json::json!(end - start));
+        Ok(out)
+    }
+}
+
+/// Date parsing operation.
+/// Parses `in` with a strftime-style `format` into a Unix timestamp. Formats
+/// without an offset are read as UTC, and date-only formats as midnight.
+/// Without a `format`, `in` must be RFC 3339 / ISO 8601.
+/// Inputs: `in` (string), `format` (optional string)
+/// Outputs: `out` (Unix timestamp in seconds)
+struct ParseDateOp;
+impl Operation for ParseDateOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Number)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let text: &str = get_input(inputs, "in")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "ParseDate".to_string(),
+            expected: "string".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        let format: Option<&str> = inputs.get("format").and_then(|v: &Value| -> Option<&str> { v.as_str() });
+
+        let parsed: std::result::Result<chrono::DateTime<chrono::Utc>, chrono::ParseError> = match format {
+            None => chrono::DateTime::parse_from_rfc3339(text).map(|dt: chrono::DateTime<chrono::FixedOffset>| dt.to_utc()),
+            Some(fmt) => chrono::DateTime::parse_from_str(text, fmt)
+                .map(|dt: chrono::DateTime<chrono::FixedOffset>| dt.to_utc())
+                .or_else(|_| chrono::NaiveDateTime::parse_from_str(text, fmt).map(|dt: chrono::NaiveDateTime| dt.and_utc()))
+                .or_else(|e: chrono::ParseError| {
+                    chrono::NaiveDate::parse_from_str(text, fmt)
+                        .map(|d: chrono::NaiveDate| d.and_time(chrono::NaiveTime::MIN).and_utc())
+                        .map_err(|_| e)
+                }),
+        };
+        let dt: chrono::DateTime<chrono::Utc> = parsed.map_err(|e: chrono::ParseError| Error::OperationError {
+            node: "unknown".to_string(),
+            reason: format!("Cannot parse date '{}' with format '{}': {}", text, format.unwrap_or("RFC 3339"), e),
+            inputs_snapshot: None,
+        })?;
+
+        let res: Value = match dt.timestamp_subsec_nanos() {
+            0 => serde_json::json!(dt.timestamp()),
+            nanos => serde_json::json!(dt.timestamp() as f64 + f64::from(nanos) / 1e9_f64),
+        };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), res);
+        Ok(out)
+    }
+}
+
+/// Date formatting operation.
+/// Renders a Unix timestamp in UTC with a strftime-style `format`, or as
+/// RFC 3339 when no `format` is given.
+/// Inputs: `in` (Unix timestamp in seconds), `format` (optional string)
+/// Outputs: `out` (string)
+struct FormatDateOp;
+impl Operation for FormatDateOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let ts: f64 = get_f64(inputs, "in")?;
+        let format: Option<&str> = inputs.get("format").and_then(|v: &Value| -> Option<&str> { v.as_str() });
+
+        let dt: chrono::DateTime<chrono::Utc> = chrono::DateTime::from_timestamp(
+            ts.floor() as i64,
+            ((ts - ts.floor()) * 1e9_f64) as u32,
+        ).ok_or_else(|| Error::OperationError {
+            node: "unknown".to_string(),
+            reason: format!("Timestamp {} is out of range", ts),
+            inputs_snapshot: None,
+        })?;
+
+        let res: String = match format {
+            None => dt.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            Some(fmt) => {
+                // Validate up front: formatting with a bad specifier would panic
+                let items: Vec<chrono::format::Item> = chrono::format::StrftimeItems::new(fmt).collect();
+                if items.iter().any(|item: &chrono::format::Item| matches!(item, chrono::format::Item::Error)) {
+                    return Err(Error::OperationError {
+                        node: "unknown".to_string(),
+                        reason: format!("Invalid date format '{}'", fmt),
+                        inputs_snapshot: None,
+                    });
+                }
+                dt.format_with_items(items.into_iter()).to_string()
+            }
+        };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(res));
+        Ok(out)
+    }
+}
+
+/// Environment variable operation.
+/// Inputs: `name` (string), `default` (optional string)
+/// Outputs: `out` (string)
+struct EnvOp;
+impl Operation for EnvOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["name"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let name: &str = get_input(inputs, "name")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "Env".to_string(),
+            expected: "string (variable name)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+
+        let val: String = match std::env::var(name) {
+            Ok(v) => v,
+            Err(e) => match inputs.get("default") {
+                Some(Value::String(d)) => d.clone(),
+                Some(other) => other.to_string(),
+                None => return Err(Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("Environment variable '{}' unavailable: {}", name, e),
+                    inputs_snapshot: None,
+                }),
+            },
+        };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(val));
+        Ok(out)
+    }
+}
+
+/// Runtime Input operation.
+/// Reads a value injected into the engine (see `Engine::set_input`).
+/// Inputs: `name` (string)
+/// Outputs: `out` (the injected value)
+struct InputOp;
+impl Operation for InputOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["name"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let name: &str = get_input(inputs, "name")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "Input".to_string(),
+            expected: "string (input name)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+
+        let val: Value = ctx.inputs.get(name).cloned().ok_or_else(|| Error::MissingInput {
+            node: "unknown".to_string(),
+            port: name.to_string(),
+        })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), val);
+        Ok(out)
+    }
+}
+
+/// File read operation.
+/// Paths are resolved against the context's base directory and may not escape it.
+/// Inputs: `path` (string)
+/// Outputs: `out` (file contents as string)
+struct ReadFileOp;
+
+impl ReadFileOp {
+    fn resolve(path: &str, base_dir: Option<&Path>) -> Result<PathBuf> {
+        let io_err = |reason: String| -> Error {
+            Error::Io { node: "unknown".to_string(), path: path.to_string(), reason }
+        };
+
+        let base: PathBuf = match base_dir {
+            Some(dir) => dir.to_path_buf(),
+            None => std::env::current_dir().map_err(|e: std::io::Error| io_err(e.to_string()))?,
+        };
+        let base: PathBuf = base.canonicalize().map_err(|e: std::io::Error| io_err(e.to_string()))?;
+        let full: PathBuf = base.join(path).canonicalize().map_err(|e: std::io::Error| io_err(e.to_string()))?;
+
+        if !full.starts_with(&base) {
+            return Err(io_err(format!("path is outside the allowed base directory '{}'", base.display())));
+        }
+        Ok(full)
+    }
+}
+
+impl Operation for ReadFileOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["path"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let path: &str = get_input(inputs, "path")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "ReadFile".to_string(),
+            expected: "string (path)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+
+        let full: PathBuf = ReadFileOp::resolve(path, ctx.base_dir.as_deref())?;
+        let contents: String = std::fs::read_to_string(&full).map_err(|e: std::io::Error| Error::Io {
+            node: "unknown".to_string(),
+            path: path.to_string(),
+            reason: e.to_string(),
+        })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(contents));
+        Ok(out)
+    }
+}
+
+/// HTTP GET operation.
+/// Only HTTPS URLs resolving to public addresses are allowed unless the
+/// context enables `allow_insecure_http`. The connection goes to the address
+/// that was checked, and redirects are never followed: a 3xx response is an error.
+/// Inputs: `url` (string), `headers` (optional object of strings)
+/// Outputs: `out` (response body as string)
+struct HttpGetOp;
+
+impl HttpGetOp {
+    fn is_private(ip: &std::net::IpAddr) -> bool {
+        match ip {
+            std::net::IpAddr::V4(v4) => {
+                let o: [u8; 4] = v4.octets();
+                v4.is_private() || v4.is_loopback() || v4.is_link_local()
+                    || v4.is_unspecified() || v4.is_broadcast()
+                    || (o[0] == 100 && (o[1] & 0xc0) == 64)  // shared address space 100.64.0.0/10
+            }
+            std::net::IpAddr::V6(v6) => {
+                if let Some(v4) = v6.to_ipv4_mapped() {
+                    return HttpGetOp::is_private(&std::net::IpAddr::V4(v4));
+                }
+                let first: u16 = v6.segments()[0];
+                v6.is_loopback() || v6.is_unspecified()
+                    || (first & 0xfe00) == 0xfc00  // unique local
+                    || (first & 0xffc0) == 0xfe80  // link local
+            }
+        }
+    }
+
+    /// Checks that `url` is HTTPS and resolves only to public addresses,
+    /// returning those addresses so the request can connect to them directly.
+    fn check_url(url: &str) -> Result<Vec<std::net::SocketAddr>> {
+        let rejected = |reason: &str| -> Error {
+            Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Refusing to fetch '{}': {} (enable insecure HTTP to allow)", url, reason),
+                inputs_snapshot: None,
+            }
+        };
+
+        let rest: &str = url.strip_prefix("https://").ok_or_else(|| rejected("only https:// URLs are allowed"))?;
+        let authority: &str = rest.split(['/', '?', '#']).next().unwrap_or("");
+        let host_port: &str = authority.rsplit('@').next().unwrap_or(authority);
+        let with_port: String = if host_port.starts_with('[') {
+            if host_port.contains("]:") { host_port.to_string() } else { format!("{}:443", host_port) }
+        } else if host_port.contains(':') {
+            host_port.to_string()
+        } else {
+            format!("{}:443", host_port)
+        };
+
+        let addrs: Vec<std::net::SocketAddr> = std::net::ToSocketAddrs::to_socket_addrs(&with_port)
+            .map_err(|e: std::io::Error| Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Cannot resolve '{}': {}", host_port, e),
+                inputs_snapshot: None,
+            })?
+            .collect();
+        if addrs.is_empty() {
+            return Err(rejected("host does not resolve to any address"));
+        }
+        if addrs.iter().any(|a: &std::net::SocketAddr| HttpGetOp::is_private(&a.ip())) {
+            return Err(rejected("host resolves to a private or loopback address"));
+        }
+        Ok(addrs)
+    }
+
+    fn agent(url: &str, allow_insecure_http: bool) -> Result<ureq::Agent> {
+        let builder: ureq::AgentBuilder = ureq::AgentBuilder::new()
+            .redirects(0)
+            .https_only(!allow_insecure_http);
+        if allow_insecure_http {
+            return Ok(builder.build());
+        }
+        // Pin the connection to the addresses that passed the check, so a
+        // second DNS lookup cannot swap in a private one.
+        let addrs: Vec<std::net::SocketAddr> = HttpGetOp::check_url(url)?;
+        Ok(builder
+            .resolver(move |_: &str| -> std::io::Result<Vec<std::net::SocketAddr>> { Ok(addrs.clone()) })
+            .build())
+    }
+}
+
+impl Operation for HttpGetOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn is_external(&self) -> bool {
+        true
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["url"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let url: &str = get_input(inputs, "url")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "HttpGet".to_string(),
+            expected: "string (url)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+
+        let agent: ureq::Agent = HttpGetOp::agent(url, ctx.allow_insecure_http)?;
+        let mut request: ureq::Request = agent.get(url);
+        if let Some(headers) = inputs.get("headers") {
+            let headers: &serde_json::Map<String, Value> = headers.as_object().ok_or_else(|| Error::InvalidType {
+                node: "HttpGet".to_string(),
+                expected: "object (headers)".to_string(),
+                actual: "non-object".to_string(),
+            })?;
+            for (name, val) in headers {
+                let val: String = match val {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                request = request.set(name, &val);
+            }
+        }
+
+        let body: String = match request.call() {
+            Ok(response) if (300..400).contains(&response.status()) => {
+                let location: String = response.header("location").unwrap_or("").to_string();
+                return Err(Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!(
+                        "GET '{}' returned redirect {} to '{}'; redirects are not followed",
+                        url, response.status(), location
+                    ),
+                    inputs_snapshot: None,
+                });
+            }
+            Ok(response) => response.into_string().map_err(|e: std::io::Error| Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Failed to read response from '{}': {}", url, e),
+                inputs_snapshot: None,
+            })?,
+            Err(ureq::Error::Status(code, response)) => {
+                return Err(Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("GET '{}' returned status {} {}", url, code, response.status_text()),
+                    inputs_snapshot: None,
+                });
+            }
+            Err(e) => {
+                return Err(Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("GET '{}' failed: {}", url, e),
+                    inputs_snapshot: None,
+                });
+            }
+        };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(body));
+        Ok(out)
+    }
+}
+
+/// Array Repeat operation.
+/// Builds an array holding `count` copies of `value`.
+/// Inputs: `value`, `count` (integer from 0 to 1,000,000)
+/// Outputs: `out` (array)
+struct RepeatOp;
+
+impl RepeatOp {
+    const MAX_COUNT: f64 = 1_000_000.0;
+}
+
+impl Operation for RepeatOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["value", "count"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let value: &Value = get_input(inputs, "value")?;
+
+        let count: f64 = get_f64(inputs, "count")?;
+        if !count.is_finite() || count < 0.0_f64 || count.fract() != 0.0_f64 {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Repeat count must be a non-negative integer, got {}", count),
+                inputs_snapshot: None,
+            });
+        }
+        if count > RepeatOp::MAX_COUNT {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Repeat count must be at most {}, got {}", RepeatOp::MAX_COUNT, count),
+                inputs_snapshot: None,
+            });
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(vec![value.clone(); count as usize]));
+        Ok(out)
+    }
+}
+
+/// Array Chunk operation.
+/// Splits a list into consecutive chunks of at most `size` elements.
+/// Inputs: `list`, `size` (positive integer)
+/// Outputs: `out` (array of arrays)
+struct ChunkOp;
+impl Operation for ChunkOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "size"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "Chunk".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let size: f64 = get_f64(inputs, "size")?;
+        if size < 1.0_f64 || size.fract() != 0.0_f64 {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Chunk size must be a positive integer, got {}", size),
+                inputs_snapshot: None,
+            });
+        }
+
+        let chunks: Vec<Value> = list.chunks(size as usize)
+            .map(|chunk: &[Value]| Value::Array(chunk.to_vec()))
+            .collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(chunks));
+        Ok(out)
+    }
+}
+
+/// Array sliding Window operation.
+/// Produces every contiguous window of `size` elements; a `size` larger than
+/// the list yields an empty array.
+/// Inputs: `list`, `size` (positive integer)
+/// Outputs: `out` (array of arrays)
+struct WindowOp;
+impl Operation for WindowOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "size"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "Window".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        let size: f64 = get_f64(inputs, "size")?;
+        if size < 1.0_f64 || size.fract() != 0.0_f64 {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Window size must be a positive integer, got {}", size),
+                inputs_snapshot: None,
+            });
+        }
+
+        let windows: Vec<Value> = list.windows(size as usize)
+            .map(|window: &[Value]| Value::Array(window.to_vec()))
+            .collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(windows));
+        Ok(out)
+    }
+}
+
+/// Array Transpose operation.
+/// Swaps rows and columns of a list of equal-length arrays; an empty list
+/// transposes to an empty list.
+/// Inputs: `list` (array of arrays)
+/// Outputs: `out` (array of arrays)
+struct TransposeOp;
+impl Operation for TransposeOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let not_rows = || -> Error {
+            Error::InvalidType {
+                node: "Transpose".to_string(),
+                expected: "array of arrays".to_string(),
+                actual: "non-array".to_string(),
+            }
+        };
+        let rows: Vec<&Vec<Value>> = get_input(inputs, "list")?.as_array().ok_or_else(not_rows)?
+            .iter()
+            .map(|row: &Value| row.as_array().ok_or_else(not_rows))
+            .collect::<Result<Vec<&Vec<Value>>>>()?;
+
+        let width: usize = rows.first().map_or(0, |row: &&Vec<Value>| row.len());
+        if let Some((i, row)) = rows.iter().enumerate().find(|(_, row): &(usize, &&Vec<Value>)| row.len() != width) {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Cannot transpose ragged rows: row 0 has {} elements but row {} has {}", width, i, row.len()),
+                inputs_snapshot: None,
+            });
+        }
+
+        let columns: Vec<Value> = (0..width)
+            .map(|col: usize| Value::Array(rows.iter().map(|row: &&Vec<Value>| row[col].clone()).collect()))
+            .collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(columns));
+        Ok(out)
+    }
+}
+
+/// Array Interleave operation.
+/// Takes elements round-robin from each array, skipping arrays once they
+/// are exhausted, so inputs may differ in length.
+/// Inputs: `lists` (array of arrays)
+/// Outputs: `out` (array)
+struct InterleaveOp;
+impl Operation for InterleaveOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["lists"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let not_lists = || -> Error {
+            Error::InvalidType {
+                node: "Interleave".to_string(),
+                expected: "array of arrays".to_string(),
+                actual: "non-array".to_string(),
+            }
+        };
+        let lists: Vec<&Vec<Value>> = get_input(inputs, "lists")?.as_array().ok_or_else(not_lists)?
+            .iter()
+            .map(|list: &Value| list.as_array().ok_or_else(not_lists))
+            .collect::<Result<Vec<&Vec<Value>>>>()?;
+
+        let longest: usize = lists.iter().map(|list: &&Vec<Value>| list.len()).max().unwrap_or(0);
+        let interleaved: Vec<Value> = (0..longest)
+            .flat_map(|i: usize| lists.iter().filter_map(move |list: &&Vec<Value>| list.get(i).cloned()))
+            .collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(interleaved));
+        Ok(out)
+    }
+}
+
+/// Set operations (Union, Intersect, Difference) over arrays.
+/// Elements are compared with `values_equal`; the result keeps the order of
+/// first occurrence and contains no duplicates.
+/// Inputs: `a` (array), `b` (array)
+/// Outputs: `out` (array)
+enum SetOp { Union, Intersect, Difference }
+impl Operation for SetOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["a", "b"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let get_array = |name: &str| -> Result<&Vec<Value>> {
+            get_input(inputs, name)?.as_array().ok_or_else(|| Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "array".to_string(),
+                actual: "non-array".to_string(),
+            })
+        };
+        let a: &Vec<Value> = get_array("a")?;
+        let b: &Vec<Value> = get_array("b")?;
+        let in_b = |v: &Value| -> bool { b.iter().any(|w: &Value| values_equal(v, w)) };
+
+        let candidates: Vec<&Value> = match self {
+            SetOp::Union => a.iter().chain(b.iter()).collect(),
+            SetOp::Intersect => a.iter().filter(|v: &&Value| in_b(v)).collect(),
+            SetOp::Difference => a.iter().filter(|v: &&Value| !in_b(v)).collect(),
+        };
+
+        let mut res: Vec<Value> = Vec::new();
+        for v in candidates {
+            if !res.iter().any(|seen: &Value| values_equal(seen, v)) {
+                res.push(v.clone());
+            }
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(res));
+        Ok(out)
+    }
+}
+
+/// Object construction operation.
+/// Zips parallel `keys` and `values` arrays into an object.
+/// Inputs: `keys` (array of strings), `values` (array, same length)
+/// Outputs: `out` (object)
+struct MakeObjectOp;
+impl Operation for MakeObjectOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["keys", "values"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let keys: &Vec<Value> = get_input(inputs, "keys")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "MakeObject".to_string(),
+            expected: "array (keys)".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+        let values: &Vec<Value> = get_input(inputs, "values")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "MakeObject".to_string(),
+            expected: "array (values)".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+
+        if keys.len() != values.len() {
+            return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("MakeObject got {} keys but {} values", keys.len(), values.len()),
+                inputs_snapshot: None,
+            });
+        }
+
+        let mut object: serde_json::Map<String, Value> = serde_json::Map::new();
+        for (key, val) in keys.iter().zip(values) {
+            let key: &str = key.as_str().ok_or_else(|| Error::InvalidType {
+                node: "MakeObject".to_string(),
+                expected: "string (key)".to_string(),
+                actual: format!("{}", key),
+            })?;
+            let _: Option<Value> = object.insert(key.to_string(), val.clone());
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Object(object));
+        Ok(out)
+    }
+}
+
+/// Object projection operations (Pick, Omit).
+/// Pick keeps only the listed keys that are present; Omit keeps every other key.
+/// Inputs: `obj` (object), `keys` (array of strings)
+/// Outputs: `out` (object)
+enum ProjectOp { Pick, Omit }
+impl Operation for ProjectOp {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        (port == "keys").then(|| SpellType::Array(Box::new(SpellType::String)))
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["obj", "keys"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let obj_val: &Value = get_input(inputs, "obj")?;
+        let obj: &serde_json::Map<String, Value> = obj_val.as_object().ok_or_else(|| Error::InvalidType {
+            node: "unknown".to_string(),
+            expected: "object".to_string(),
+            actual: format!("{:?}", obj_val),
+        })?;
+        let keys_val: &Value = get_input(inputs, "keys")?;
+        let keys: Vec<&str> = keys_val.as_array()
+            .and_then(|keys: &Vec<Value>| keys.iter().map(Value::as_str).collect())
+            .ok_or_else(|| Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "array of strings".to_string(),
+                actual: format!("{:?}", keys_val),
+            })?;
+
+        let keep: bool = matches!(self, ProjectOp::Pick);
+        let projected: serde_json::Map<String, Value> = obj.iter()
+            .filter(|(k, _): &(&String, &Value)| keys.contains(&k.as_str()) == keep)
+            .map(|(k, v): (&String, &Value)| (k.clone(), v.clone()))
+            .collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Object(projected));
+        Ok(out)
+    }
+}
+
+/// Recursive object merge.
+/// Keys of `b` override those of `a`; where both hold objects they are
+/// merged recursively. Arrays are replaced, or appended to when
+/// `concat_arrays` is set.
+/// Inputs: `a`, `b` (objects), `concat_arrays` (optional boolean, default false)
+/// Outputs: `out` (object)
+struct DeepMergeOp;
+impl DeepMergeOp {
+    fn merge(a: &mut Value, b: &Value, concat_arrays: bool) {
+        match (a, b) {
+            (Value::Object(a), Value::Object(b)) => {
+                for (key, b_val) in b {
+                    match a.get_mut(key) {
+                        Some(a_val) => DeepMergeOp::merge(a_val, b_val, concat_arrays),
+                        None => {
+                            let _: Option<Value> = a.insert(key.clone(), b_val.clone());
+                        }
+                    }
+                }
+            }
+            (Value::Array(a), Value::Array(b)) if concat_arrays => a.extend(b.iter().cloned()),
+            (a, b) => *a = b.clone(),
+        }
+    }
+}
+
+impl Operation for DeepMergeOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["a", "b"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let get_object = |name: &str| -> Result<&Value> {
+            let val: &Value = get_input(inputs, name)?;
+            if val.is_object() {
+                Ok(val)
+            } else {
+                Err(Error::InvalidType {
+                    node: "unknown".to_string(),
+                    expected: "object".to_string(),
+                    actual: format!("{:?}", val),
+                })
+            }
+        };
+        let mut merged: Value = get_object("a")?.clone();
+        let b: &Value = get_object("b")?;
+        let concat_arrays: bool = if inputs.contains_key("concat_arrays") { get_bool(inputs, "concat_arrays")? } else { false };
+
+        DeepMergeOp::merge(&mut merged, b, concat_arrays);
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), merged);
+        Ok(out)
+    }
+}
+
+/// Content Hash operation.
+/// Hashes the canonical JSON form of a value: object keys sorted at every
+/// level and numbers written as `canonical_number` does, so values equal
+/// under `values_equal` hash identically regardless of key order.
+/// Inputs: `in`, `algo` (optional: `sha256` (default) or `sha512`)
+/// Outputs: `out` (lowercase hex string)
+struct HashOp;
+impl HashOp {
+    fn canonical(value: &Value, buf: &mut String) {
+        match value {
+            Value::Object(map) => {
+                let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+                entries.sort_by(|a: &(&String, &Value), b: &(&String, &Value)| a.0.cmp(b.0));
+                buf.push('{');
+                for (i, (key, item)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        buf.push(',');
+                    }
+                    buf.push_str(&Value::String(key.clone()).to_string());
+                    buf.push(':');
+                    HashOp::canonical(item, buf);
+                }
+                buf.push('}');
+            }
+            Value::Array(items) => {
+                buf.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(',');
+                    }
+                    HashOp::canonical(item, buf);
+                }
+                buf.push(']');
+            }
+            Value::Number(n) => buf.push_str(&canonical_number(n)),
+            other => buf.push_str(&other.to_string()),
+        }
+    }
+}
+
+impl Operation for HashOp {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        (port == "algo").then_some(SpellType::String)
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let value: &Value = get_input(inputs, "in")?;
+        let algo: &str = if inputs.contains_key("algo") {
+            get_input(inputs, "algo")?.as_str().ok_or_else(|| Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "string".to_string(),
+                actual: "non-string".to_string(),
+            })?
+        } else {
+            "sha256"
+        };
+
+        let mut text: String = String::new();
+        HashOp::canonical(value, &mut text);
+        let digest: Vec<u8> = match algo {
+            "sha256" => sha2::Sha256::digest(text.as_bytes()).to_vec(),
+            "sha512" => sha2::Sha512::digest(text.as_bytes()).to_vec(),
+            other => return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Unknown hash algorithm '{}' (expected sha256 or sha512)", other),
+                inputs_snapshot: None,
+            }),
+        };
+        let hex: String = digest.iter().map(|b: &u8| format!("{:02x}", b)).collect();
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(hex));
+        Ok(out)
+    }
+}
+
+/// Base64 operations (Base64Encode, Base64Decode) using the standard padded alphabet.
+/// Decoding fails on malformed input or bytes that are not valid UTF-8.
+/// Inputs: `in` (string)
+/// Outputs: `out` (string)
+enum Base64Op { Encode, Decode }
+impl Operation for Base64Op {
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        (port == "in").then_some(SpellType::String)
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let text: &str = get_input(inputs, "in")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "unknown".to_string(),
+            expected: "string".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+
+        let res: String = match self {
+            Base64Op::Encode => base64::engine::general_purpose::STANDARD.encode(text),
+            Base64Op::Decode => {
+                let bytes: Vec<u8> = base64::engine::general_purpose::STANDARD.decode(text)
+                    .map_err(|e: base64::DecodeError| Error::OperationError {
+                        node: "unknown".to_string(),
+                        reason: format!("Invalid base64: {}", e),
+                        inputs_snapshot: None,
+                    })?;
+                String::from_utf8(bytes).map_err(|e: std::string::FromUtf8Error| Error::OperationError {
+                    node: "unknown".to_string(),
+                    reason: format!("Decoded base64 is not valid UTF-8: {}", e.utf8_error()),
+                    inputs_snapshot: None,
+                })?
+            }
+        };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(res));
+        Ok(out)
+    }
+}
+
+/// String escaping operations (Escape, Unescape).
+/// Modes:
+/// - `json`: the contents of a JSON string literal, without the quotes
+/// - `shell`: a single POSIX shell word (single-quoted when escaping)
+/// - `url`: percent-encoding of everything but unreserved characters
+///
+/// Inputs: `in` (string), `mode` (string)
+/// Outputs: `out` (string)
+enum EscapeOp { Escape, Unescape }
+impl EscapeOp {
+    fn shell_unescape(text: &str) -> std::result::Result<String, String> {
+        let mut res: String = String::new();
+        let mut chars: std::str::Chars<'_> = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' => loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => res.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                },
+                '"' => loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('$' | '`' | '"' | '\\')) => res.push(c),
+                            Some('\n') => {}
+                            Some(c) => { res.push('\\'); res.push(c); }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => res.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                },
+                '\\' => match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => res.push(c),
+                    None => return Err("trailing backslash".to_string()),
+                },
+                c if c.is_whitespace() => return Err("unquoted whitespace separates shell words".to_string()),
+                c => res.push(c),
+            }
+        }
+        Ok(res)
+    }
+
+    fn url_unescape(text: &str) -> std::result::Result<String, String> {
+        let bytes: &[u8] = text.as_bytes();
+        let mut res: Vec<u8> = Vec::with_capacity(bytes.len());
+        let mut i: usize = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                let digits: &[u8] = &bytes[i + 1..bytes.len().min(i + 3)];
+                if !digits.iter().all(u8::is_ascii_hexdigit) {
+                    // `%` and hex digits are ASCII, so these slices fall on char boundaries.
+                    let shown: String = text[i + 1..].chars().take(2).collect();
+                    return Err(format!("invalid escape '%{}' at byte {}", shown, i));
+                }
+                if digits.len() < 2 {
+                    return Err(format!("truncated escape at byte {}", i));
+                }
+                let hex: &str = &text[i + 1..i + 3];
+                res.push(u8::from_str_radix(hex, 16).map_err(|_| format!("invalid escape '%{}' at byte {}", hex, i))?);
+                i += 3;
+            } else {
+                res.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(res).map_err(|e: std::string::FromUtf8Error| format!("not valid UTF-8: {}", e.utf8_error()))
+    }
+}
+
+impl Operation for EscapeOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        matches!(port, "in" | "mode").then_some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in", "mode"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let get_str = |name: &str| -> Result<&str> {
+            get_input(inputs, name)?.as_str().ok_or_else(|| Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "string".to_string(),
+                actual: "non-string".to_string(),
+            })
+        };
+        let text: &str = get_str("in")?;
+        let mode: &str = get_str("mode")?;
+
+        let res: std::result::Result<String, String> = match (self, mode) {
+            (EscapeOp::Escape, "json") => {
+                let quoted: String = Value::String(text.to_string()).to_string();
+                Ok(quoted[1..quoted.len() - 1].to_string())
+            }
+            (EscapeOp::Unescape, "json") => serde_json::from_str::<String>(&format!("\"{}\"", text))
+                .map_err(|e: serde_json::Error| e.to_string()),
+            (EscapeOp::Escape, "shell") => Ok(format!("'{}'", text.replace('\'', "'\\''"))),
+            (EscapeOp::Unescape, "shell") => EscapeOp::shell_unescape(text),
+            (EscapeOp::Escape, "url") => Ok(text.bytes()
+                .map(|b: u8| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+                    _ => format!("%{:02X}", b),
+                })
+                .collect()),
+            (EscapeOp::Unescape, "url") => EscapeOp::url_unescape(text),
+            (_, other) => return Err(Error::OperationError {
+                node: "unknown".to_string(),
+                reason: format!("Unsupported escape mode '{}', expected \"json\", \"shell\" or \"url\"", other),
+                inputs_snapshot: None,
+            }),
+        };
+        let res: String = res.map_err(|reason: String| Error::OperationError {
+            node: "unknown".to_string(),
+            reason: format!("Cannot unescape {} text: {}", mode, reason),
+            inputs_snapshot: None,
+        })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(res));
+        Ok(out)
+    }
+}
+
+/// Pretty-printed JSON operation.
+/// Serializes `in` with `indent` spaces per level. With `reparse` set, a
+/// string `in` is first parsed as JSON text, so it is reformatted rather
+/// than printed as a quoted string.
+/// Inputs: `in`, `indent` (optional number from 0 to 16, default 2), `reparse` (optional boolean, default false)
+/// Outputs: `out` (string)
+struct PrettyJsonOp;
+
+impl PrettyJsonOp {
+    const MAX_INDENT: f64 = 16.0;
+}
+
+impl Operation for PrettyJsonOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let val: &Value = get_input(inputs, "in")?;
+        let indent: f64 = if inputs.contains_key("indent") { get_f64(inputs, "indent")? } else { 2.0_f64 };
+        let reparse: bool = if inputs.contains_key("reparse") { get_bool(inputs, "reparse")? } else { false };
+        let fail = |reason: String| -> Error {
+            Error::OperationError {
+                node: "unknown".to_string(),
+                reason,
+                inputs_snapshot: None,
+            }
+        };
+
+        if indent < 0.0_f64 || indent.fract() != 0.0_f64 {
+            return Err(fail(format!("PrettyJson indent must be a non-negative integer, got {}", indent)));
+        }
+        if indent > PrettyJsonOp::MAX_INDENT {
+            return Err(fail(format!("PrettyJson indent must be at most {}, got {}", PrettyJsonOp::MAX_INDENT, indent)));
+        }
+
+        let parsed: Value;
+        let val: &Value = match val {
+            Value::String(text) if reparse => {
+                parsed = serde_json::from_str(text)
+                    .map_err(|e: serde_json::Error| fail(format!("Input is not valid JSON: {}", e)))?;
+                &parsed
+            }
+            other => other,
+        };
+
+        let indent: Vec<u8> = vec![b' '; indent as usize];
+        let mut buf: Vec<u8> = Vec::new();
+        let formatter: serde_json::ser::PrettyFormatter<'_> = serde_json::ser::PrettyFormatter::with_indent(&indent);
+        let mut ser: serde_json::Serializer<&mut Vec<u8>, serde_json::ser::PrettyFormatter<'_>> =
+            serde_json::Serializer::with_formatter(&mut buf, formatter);
+        val.serialize(&mut ser).map_err(|e: serde_json::Error| fail(e.to_string()))?;
+        let text: String = String::from_utf8(buf).map_err(|e: std::string::FromUtf8Error| fail(e.to_string()))?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(text));
+        Ok(out)
+    }
+}
+
+/// Regular expression replacement.
+/// Replaces every match of `pattern` in `in` with `replacement`, which may
+/// refer to capture groups as `$1` or `${name}` (`$$` for a literal `$`).
+/// Inputs: `in`, `pattern`, `replacement` (strings)
+/// Outputs: `out` (string)
+struct RegexReplaceOp;
+impl Operation for RegexReplaceOp {
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn input_type(&self, port: &str) -> Option<SpellType> {
+        matches!(port, "in" | "pattern" | "replacement").then_some(SpellType::String)
+    }
+
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in", "pattern", "replacement"]
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let get_str = |name: &str| -> Result<&str> {
+            get_input(inputs, name)?.as_str().ok_or_else(|| Error::InvalidType {
+                node: "unknown".to_string(),
+                expected: "string".to_string(),
+                actual: "non-string".to_string(),
+            })
+        };
+        let text: &str = get_str("in")?;
+        let pattern: &str = get_str("pattern")?;
+        let replacement: &str = get_str("replacement")?;
+
+        let re: regex::Regex = regex::Regex::new(pattern).map_err(|e: regex::Error| Error::OperationError {
+            node: "unknown".to_string(),
+            reason: format!("Invalid regex pattern: {}", e),
+            inputs_snapshot: None,
+        })?;
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(re.replace_all(text, replacement).into_owned()));
+        Ok(out)
+    }
+}
+
+/// Template Render operation.
+/// Fills `{{field}}` placeholders in `template` from each object in `list`
+/// and joins the results with `sep`. Strings are inserted as-is; other
+/// values as JSON, with numbers in the context's number format.
+/// Inputs: `list` (array of objects), `template` (string), `sep` (optional string, default newline)
+/// Outputs: `out` (string)
+struct RenderOp;
+impl Operation for RenderOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list", "template"]
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "Render".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+        let template: &str = get_input(inputs, "template")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "Render".to_string(),
+            expected: "string (template)".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        let sep: &str = match inputs.get("sep") {
+            Some(v) => v.as_str().ok_or_else(|| Error::InvalidType {
+                node: "Render".to_string(),
+                expected: "string (separator)".to_string(),
+                actual: "non-string".to_string(),
+            })?,
+            None => "\n",
+        };
+
+        let failure = |reason: String| -> Error {
+            Error::OperationError { node: "unknown".to_string(), reason, inputs_snapshot: None }
+        };
+
+        let mut rendered: Vec<String> = Vec::with_capacity(list.len());
+        for (i, item) in list.iter().enumerate() {
+            let record: &serde_json::Map<String, Value> = item.as_object()
+                .ok_or_else(|| failure(format!("Element {} is not an object", i)))?;
+
+            let mut text: String = String::with_capacity(template.len());
+            let mut rest: &str = template;
+            while let Some(start) = rest.find("{{") {
+                text.push_str(&rest[..start]);
+                let after: &str = &rest[start + 2..];
+                let end: usize = after.find("}}")
+                    .ok_or_else(|| failure("Template has an unclosed '{{'".to_string()))?;
+                let field: &str = after[..end].trim();
+                match record.get(field) {
+                    Some(Value::String(s)) => text.push_str(s),
+                    Some(other) => text.push_str(&ctx.number_format.render(other)),
+                    None => return Err(failure(format!("Element {} has no field '{}'", i, field))),
+                }
+                rest = &after[end + 2..];
+            }
+            text.push_str(rest);
+            rendered.push(text);
+        }
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(rendered.join(sep)));
+        Ok(out)
+    }
+}
+
+/// CSV export operation.
+/// Writes a header row of `columns` followed by one row per object. Strings
+/// are written as-is, null or missing fields as empty, and other values as
+/// JSON with numbers in the context's number format. Fields containing a
+/// comma, quote or line break are quoted.
+/// Inputs: `list` (array of objects), `columns` (optional array of strings, default: every key in order of first appearance)
+/// Outputs: `out` (string)
+struct ToCsvOp;
+impl ToCsvOp {
+    fn quote(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+}
+
+impl Operation for ToCsvOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["list"]
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::String)
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        self.execute_with(inputs, &Context::default())
+    }
+
+    fn execute_with(&self, inputs: &HashMap<String, Value>, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let list: &Vec<Value> = get_input(inputs, "list")?.as_array().ok_or_else(|| Error::InvalidType {
+            node: "ToCsv".to_string(),
+            expected: "array".to_string(),
+            actual: "non-array".to_string(),
+        })?;
+        let records: Vec<&serde_json::Map<String, Value>> = list.iter()
+            .map(|item: &Value| item.as_object().ok_or_else(|| Error::InvalidType {
+                node: "ToCsv".to_string(),
+                expected: "array of objects".to_string(),
+                actual: format!("{:?}", item),
+            }))
+            .collect::<Result<Vec<&serde_json::Map<String, Value>>>>()?;
+
+        let columns: Vec<String> = match inputs.get("columns") {
+            Some(val) => val.as_array()
+                .and_then(|cols: &Vec<Value>| cols.iter().map(|c: &Value| c.as_str().map(str::to_string)).collect())
+                .ok_or_else(|| Error::InvalidType {
+                    node: "ToCsv".to_string(),
+                    expected: "array of strings (columns)".to_string(),
+                    actual: format!("{:?}", val),
+                })?,
+            None => {
+                let mut columns: Vec<String> = Vec::new();
+                for key in records.iter().flat_map(|record: &&serde_json::Map<String, Value>| record.keys()) {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+                columns
+            }
+        };
+
+        let header: Vec<String> = columns.iter().map(|c: &String| ToCsvOp::quote(c)).collect();
+        let mut lines: Vec<String> = vec![header.join(",")];
+        for record in records {
+            let fields: Vec<String> = columns.iter()
+                .map(|c: &String| match record.get(c) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) => ToCsvOp::quote(s),
+                    Some(other) => ToCsvOp::quote(&ctx.number_format.render(other)),
+                })
+                .collect();
+            lines.push(fields.join(","));
+        }
+
+        let mut csv: String = lines.join("\n");
+        csv.push('\n');
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::String(csv));
+        Ok(out)
+    }
+}
+
+/// CSV parsing operation.
+/// Splits `in` into records of string fields, honouring quoted fields (with
+/// `""` for a quote) that may contain commas and line breaks. With `header`
+/// the first record names the fields of one object per following record;
+/// without it every record is returned as an array. Every record must have
+/// as many fields as the first.
+/// Inputs: `in` (string), `header` (optional boolean, default true)
+/// Outputs: `out` (array of objects, or array of arrays of strings)
+struct ParseCsvOp;
+impl ParseCsvOp {
+    fn records(text: &str) -> std::result::Result<Vec<Vec<String>>, String> {
+        let mut records: Vec<Vec<String>> = Vec::new();
+        let mut record: Vec<String> = Vec::new();
+        let mut field: String = String::new();
+        let mut quoted: bool = false;
+        let mut chars: std::iter::Peekable<std::str::Chars<'_>> = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    let _: Option<char> = chars.next();
+                }
+                '"' if quoted => quoted = false,
+                '"' if field.is_empty() => quoted = true,
+                ',' if !quoted => record.push(std::mem::take(&mut field)),
+                '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+                '\n' if !quoted => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                c => field.push(c),
+            }
+        }
+        if quoted {
+            return Err(format!("Record {} has an unterminated quoted field", records.len() + 1));
+        }
+        if !field.is_empty() || !record.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+impl Operation for ParseCsvOp {
+    fn required_inputs(&self) -> &'static [&'static str] {
+        &["in"]
+    }
+
+    fn output_type(&self) -> Option<SpellType> {
+        Some(SpellType::Array(Box::new(SpellType::Any)))
+    }
+
+    fn execute(&self, inputs: &HashMap<String, Value>) -> Result<HashMap<String, Value>> {
+        let text: &str = get_input(inputs, "in")?.as_str().ok_or_else(|| Error::InvalidType {
+            node: "ParseCsv".to_string(),
+            expected: "string".to_string(),
+            actual: "non-string".to_string(),
+        })?;
+        let header: bool = if inputs.contains_key("header") { get_bool(inputs, "header")? } else { true };
+
+        let failure = |reason: String| -> Error {
+            Error::OperationError { node: "unknown".to_string(), reason, inputs_snapshot: None }
+        };
+        let records: Vec<Vec<String>> = ParseCsvOp::records(text).map_err(failure)?;
+        if let Some(first) = records.first() {
+            if let Some(i) = records.iter().position(|r: &Vec<String>| r.len() != first.len()) {
+                return Err(failure(format!("Record {} has {} fields, expected {}", i + 1, records[i].len(), first.len())));
+            }
+        }
+
+        let rows: Vec<Value> = match records.split_first() {
+            Some((names, rest)) if header => rest.iter()
+                .map(|r: &Vec<String>| {
+                    Value::Object(names.iter().cloned().zip(r.iter().cloned().map(Value::String)).collect())
+                })
+                .collect(),
+            _ => records.into_iter()
+                .map(|r: Vec<String>| Value::Array(r.into_iter().map(Value::String).collect()))
+                .collect(),
+        };
+
+        let mut out: HashMap<String, Value> = HashMap::new();
+        let _: Option<Value> = out.insert("out".to_string(), Value::Array(rows));
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Runs the registered operation `op` on the inputs of a JSON object.
+    fn run_with(op: &str, inputs: Value, ctx: &Context) -> Result<HashMap<String, Value>> {
+        let inputs: HashMap<String, Value> = serde_json::from_value(inputs).unwrap();
+        Ops::default().get(op).unwrap().execute_with(&inputs, ctx)
+    }
+
+    fn run(op: &str, inputs: Value) -> Result<HashMap<String, Value>> {
+        run_with(op, inputs, &Context::default())
+    }
+
+    #[test]
+    fn sort_by_orders_records_by_field() {
+        let records: Value = json!([{"name": "b", "score": 7}, {"name": "a", "score": 3}, {"name": "c", "score": 9.5}]);
+        let names = |sorted: Value| -> Vec<Value> {
+            sorted.as_array().unwrap().iter().map(|r: &Value| r["name"].clone()).collect()
+        };
+        let ascending: Value = run("SortBy", json!({"list": records, "key": "score"})).unwrap().remove("out").unwrap();
+        assert_eq!(names(ascending), vec![json!("a"), json!("b"), json!("c")]);
+        let descending: Value = run("SortBy", json!({"list": records, "key": "score", "desc": true})).unwrap().remove("out").unwrap();
+        assert_eq!(names(descending), vec![json!("c"), json!("b"), json!("a")]);
+    }
+
+    #[test]
+    fn sort_by_extractor_and_incomparable_keys() {
+        let sorted: Value = run("SortBy", json!({"list": [3, 1, 2], "apply_op": "Mul", "arg": "a", "params": {"b": -1}})).unwrap().remove("out").unwrap();
+        assert_eq!(sorted, json!([3, 2, 1]));
+        let mixed: Result<HashMap<String, Value>> = run("SortBy", json!({"list": [{"k": 1}, {"k": "x"}], "key": "k"}));
+        assert!(matches!(mixed, Err(Error::InvalidType { .. })), "{:?}", mixed);
+    }
+
+    #[test]
+    fn random_is_reproducible_with_a_seed() {
+        let inputs: Value = json!({"min": 10, "max": 20, "seed": 42});
+        let first: Value = run("Random", inputs.clone()).unwrap().remove("out").unwrap();
+        let second: Value = run("Random", inputs).unwrap().remove("out").unwrap();
+        assert_eq!(first, second);
+        let value: f64 = first.as_f64().unwrap();
+        assert!((10.0..20.0).contains(&value), "{}", value);
+        let other: Value = run("Random", json!({"min": 10, "max": 20, "seed": 43})).unwrap().remove("out").unwrap();
+        assert_ne!(first, other);
+    }
+
+    fn reason(result: Result<HashMap<String, Value>>) -> String {
+        match result {
+            Err(Error::OperationError { reason, .. }) => reason,
+            other => panic!("expected an operation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn assert_passes_true_through() {
+        assert_eq!(run("Assert", json!({"cond": true, "message": "unused"})).unwrap()["out"], json!(true));
+    }
+
+    #[test]
+    fn assert_fails_with_its_message() {
+        assert_eq!(reason(run("Assert", json!({"cond": false, "message": "totals must balance"}))), "totals must balance");
+    }
+
+    #[test]
+    fn coalesce_skips_nulls() {
+        assert_eq!(run("Coalesce", json!({"a": null, "b": 2, "c": 3})).unwrap()["out"], json!(2));
+        assert_eq!(run("Coalesce", json!({"values": [null, "x"]})).unwrap()["out"], json!("x"));
+    }
+
+    #[test]
+    fn coalesce_of_only_nulls_is_null() {
+        assert_eq!(run("Coalesce", json!({"a": null, "b": null})).unwrap()["out"], Value::Null);
+        assert_eq!(run("Coalesce", json!({"values": [null, null]})).unwrap()["out"], Value::Null);
+    }
+
+    #[test]
+    fn type_of_names_runtime_types() {
+        let type_of = |value: Value| -> Value { run("TypeOf", json!({"in": value})).unwrap().remove("out").unwrap() };
+        assert_eq!(type_of(json!(1.5)), json!("Number"));
+        assert_eq!(type_of(json!("text")), json!("String"));
+        assert_eq!(type_of(Value::Null), json!("Unit"));
+        assert_eq!(type_of(json!([1, 2])), json!("Array<Number>"));
+    }
+
+    #[test]
+    fn cast_string_to_number() {
+        assert_eq!(run("Cast", json!({"in": "41.5", "to": "Number"})).unwrap()["out"], json!(41.5));
+        assert_eq!(run("Cast", json!({"in": 0, "to": "Boolean"})).unwrap()["out"], json!(false));
+    }
+
+    #[test]
+    fn cast_array_to_number_fails() {
+        let result: Result<HashMap<String, Value>> = run("Cast", json!({"in": [1, 2], "to": "Number"}));
+        assert!(matches!(result, Err(Error::OperationError { .. })), "{:?}", result);
+    }
+
+    #[test]
+    fn while_computes_a_factorial() {
+        // Shifts [-4, ..., 0] up one step per iteration until no element is below 1,
+        // which leaves the factors 1..5, then multiplies them out
+        let below_one: Value = json!({ "literal": { "b": 1 }, "type": "Any" });
+        let cond: Value = json!({
+            "below": {
+                "op": "Filter",
+                "list": { "ref": "state", "type": "Array<Number>" },
+                "apply_op": { "literal": "Lt", "type": "String" },
+                "arg": { "literal": "a", "type": "String" },
+                "params": below_one
+            },
+            "count": { "op": "Len", "list": { "ref": "below", "type": "Array<Number>" } },
+            "out": { "op": "Gt", "a": { "ref": "count", "type": "Number" }, "b": { "literal": 0, "type": "Number" } }
+        });
+        let body: Value = json!({
+            "out": {
+                "op": "Map",
+                "list": { "ref": "state", "type": "Array<Number>" },
+                "apply_op": { "literal": "Add", "type": "String" },
+                "arg": { "literal": "a", "type": "String" },
+                "params": { "literal": { "b": 1 }, "type": "Any" }
+            }
+        });
+        let list: Value = run("While", json!({"state": [-4, -3, -2, -1, 0], "cond": cond, "body": body})).unwrap().remove("out").unwrap();
+        let factors: Vec<f64> = list.as_array().unwrap().iter().map(|v: &Value| v.as_f64().unwrap()).collect();
+        assert_eq!(factors, vec![1.0_f64, 2.0_f64, 3.0_f64, 4.0_f64, 5.0_f64]);
+        let product: Value = run("Reduce", json!({"list": list, "apply_op": "Mul", "initial": 1, "acc_arg": "a", "item_arg": "b"}))
+            .unwrap().remove("out").unwrap();
+        assert_eq!(product.as_f64(), Some(120.0_f64));
+    }
+
+    #[test]
+    fn while_stops_at_the_iteration_cap() {
+        let forever: Value = json!({ "out": { "op": "Const", "value": { "literal": true, "type": "Boolean" } } });
+        let same: Value = json!({ "out": { "op": "Const", "value": { "ref": "state", "type": "Any" } } });
+        let err: String = reason(run("While", json!({"state": 0, "cond": forever, "body": same, "max_iterations": 5})));
+        assert!(err.contains("max_iterations (5)"), "{}", err);
+    }
+
+    #[test]
+    fn now_reads_the_clock_each_time() {
+        let first: f64 = run("Now", json!({})).unwrap()["out"].as_f64().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second: f64 = run("Now", json!({})).unwrap()["out"].as_f64().unwrap();
+        // 2020-01-01 onwards
+        assert!(first > 1_577_836_800.0, "{}", first);
+        assert!(second > first, "{} then {}", first, second);
+        assert!(!Ops::default().get("Now").unwrap().is_cacheable(&HashMap::new()));
+    }
+
+    #[test]
+    fn elapsed_subtracts_timestamps() {
+        assert_eq!(run("Elapsed", json!({"start": 100.5, "end": 160})).unwrap()["out"], json!(59.5));
+    }
+
+    #[test]
+    fn env_reads_a_set_variable() {
+        std::env::set_var("SPELL_TEST_ENV_SET", "https://api.example.com");
+        assert_eq!(run("Env", json!({"name": "SPELL_TEST_ENV_SET", "default": "unused"})).unwrap()["out"], json!("https://api.example.com"));
+    }
+
+    #[test]
+    fn env_falls_back_to_the_default() {
+        assert_eq!(run("Env", json!({"name": "SPELL_TEST_ENV_UNSET_DEFAULT", "default": "local"})).unwrap()["out"], json!("local"));
+    }
+
+    #[test]
+    fn env_without_default_fails() {
+        let err: String = reason(run("Env", json!({"name": "SPELL_TEST_ENV_UNSET"})));
+        assert!(err.contains("SPELL_TEST_ENV_UNSET"), "{}", err);
+    }
+
+    /// A fresh directory under the system temp dir holding `files`.
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("spell-ops-{}-{}", name, std::process::id()));
+        let _: std::io::Result<()> = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn read_file_reads_inside_the_base_dir() {
+        let dir: PathBuf = fixture("read-file", &[("notes.txt", "hello\nworld")]);
+        let ctx: Context = Context { base_dir: Some(dir), ..Context::default() };
+        assert_eq!(run_with("ReadFile", json!({"path": "notes.txt"}), &ctx).unwrap()["out"], json!("hello\nworld"));
+    }
+
+    #[test]
+    fn read_file_refuses_paths_outside_the_base_dir() {
+        let dir: PathBuf = fixture("read-file-base", &[("notes.txt", "inside")]);
+        let outside: PathBuf = fixture("read-file-outside", &[("secret.txt", "outside")]);
+        let ctx: Context = Context { base_dir: Some(dir), ..Context::default() };
+        let escape: String = format!("../{}/secret.txt", outside.file_name().unwrap().to_string_lossy());
+        match run_with("ReadFile", json!({"path": escape}), &ctx) {
+            Err(Error::Io { reason, .. }) => assert!(reason.contains("outside the allowed base directory"), "{}", reason),
+            other => panic!("expected an IO error, got {:?}", other),
+        }
+        assert!(matches!(run_with("ReadFile", json!({"path": "missing.txt"}), &ctx), Err(Error::Io { .. })));
+    }
+
+    #[test]
+    fn http_get_returns_the_body() {
+        let url: String = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello");
+        assert_eq!(run_with("HttpGet", json!({"url": url, "headers": {"Accept": "text/plain"}}), &insecure()).unwrap()["out"], json!("hello"));
+    }
+
+    #[test]
+    fn http_get_reports_error_statuses() {
+        let url: String = serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        let err: String = reason(run_with("HttpGet", json!({"url": url}), &insecure()));
+        assert!(err.contains("returned status 404"), "{}", err);
+    }
+
+    #[test]
+    fn http_get_refuses_private_and_plain_http_urls() {
+        let plain: String = reason(run("HttpGet", json!({"url": "http://example.com/"})));
+        assert!(plain.contains("only https://"), "{}", plain);
+        for url in ["https://127.0.0.1/", "https://10.0.0.8:8443/x", "https://[::1]/", "https://169.254.169.254/latest"] {
+            let err: String = reason(run("HttpGet", json!({"url": url})));
+            assert!(err.contains("private or loopback"), "{}: {}", url, err);
+        }
+    }
+
+    /// Serves `response` to a single connection on a local port and returns its URL.
+    fn serve_once(response: &'static str) -> String {
+        let listener: std::net::TcpListener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr: std::net::SocketAddr = listener.local_addr().unwrap();
+        let _: std::thread::JoinHandle<()> = std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf: [u8; 4096] = [0; 4096];
+                let _: std::io::Result<usize> = std::io::Read::read(&mut stream, &mut buf);
+                let _: std::io::Result<()> = std::io::Write::write_all(&mut stream, response.as_bytes());
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    fn insecure() -> Context<'static> {
+        Context { allow_insecure_http: true, ..Context::default() }
+    }
+
+    #[test]
+    fn http_get_rejects_mapped_and_shared_addresses() {
+        for ip in ["::ffff:127.0.0.1", "::ffff:10.1.2.3", "::ffff:169.254.169.254", "100.64.0.1", "100.127.255.254"] {
+            let ip: std::net::IpAddr = ip.parse().unwrap();
+            assert!(HttpGetOp::is_private(&ip), "{} should be private", ip);
+        }
+        for ip in ["::ffff:93.184.216.34", "100.128.0.1", "100.63.255.255", "2606:4700::1111"] {
+            let ip: std::net::IpAddr = ip.parse().unwrap();
+            assert!(!HttpGetOp::is_private(&ip), "{} should be public", ip);
+        }
+        let err: String = reason(run("HttpGet", json!({"url": "https://[::ffff:127.0.0.1]:8443/"})));
+        assert!(err.contains("private or loopback"), "{}", err);
+    }
+
+    #[test]
+    fn http_get_does_not_follow_redirects() {
+        let url: String = serve_once(
+            "HTTP/1.1 302 Found\r\nLocation: http://169.254.169.254/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        let err: String = reason(run_with("HttpGet", json!({"url": url}), &insecure()));
+        assert!(err.contains("redirect 302"), "{}", err);
+        assert!(err.contains("169.254.169.254"), "{}", err);
+    }
+
+    #[test]
+    fn http_get_secure_mode_refuses_plain_http_before_connecting() {
+        let err: String = reason(run("HttpGet", json!({"url": "http://127.0.0.1:9/"})));
+        assert!(err.contains("only https://"), "{}", err);
+    }
+
+    #[test]
+    fn chunk_splits_into_fixed_sizes() {
+        assert_eq!(run("Chunk", json!({"list": [1, 2, 3, 4, 5], "size": 2})).unwrap()["out"], json!([[1, 2], [3, 4], [5]]));
+    }
+
+    #[test]
+    fn chunk_rejects_non_positive_sizes() {
+        for size in [0, -1] {
+            assert!(matches!(run("Chunk", json!({"list": [1], "size": size})), Err(Error::OperationError { .. })));
+        }
+    }
+
+    #[test]
+    fn chunk_rejects_fractional_sizes() {
+        assert_eq!(
+            reason(run("Chunk", json!({"list": [1, 2, 3], "size": 1.5}))),
+            "Chunk size must be a positive integer, got 1.5"
+        );
+        assert_eq!(run("Chunk", json!({"list": [1, 2, 3], "size": 2.0})).unwrap()["out"], json!([[1, 2], [3]]));
+    }
+
+    #[test]
+    fn window_slides_over_the_list() {
+        assert_eq!(run("Window", json!({"list": [1, 2, 3], "size": 2})).unwrap()["out"], json!([[1, 2], [2, 3]]));
+    }
+
+    #[test]
+    fn window_larger_than_the_list_is_empty() {
+        assert_eq!(run("Window", json!({"list": [1, 2, 3], "size": 4})).unwrap()["out"], json!([]));
+    }
+
+    #[test]
+    fn window_rejects_non_integral_sizes() {
+        for size in [json!(2.5), json!(0), json!(-1)] {
+            assert_eq!(
+                reason(run("Window", json!({"list": [1, 2, 3], "size": size}))),
+                format!("Window size must be a positive integer, got {}", size)
+            );
+        }
+    }
+
+    #[test]
+    fn unary_math_functions() {
+        assert_eq!(run("Sqrt", json!({"in": 4})).unwrap()["out"], json!(2.0));
+        assert_eq!(run("Log", json!({"in": 1})).unwrap()["out"], json!(0.0));
+        assert_eq!(run("Log10", json!({"in": 1000})).unwrap()["out"], json!(3.0));
+        assert_eq!(run("Exp", json!({"in": 0})).unwrap()["out"], json!(1.0));
+        assert_eq!(run("Cos", json!({"in": 0})).unwrap()["out"], json!(1.0));
+    }
+
+    #[test]
+    fn unary_math_domain_errors() {
+        for (op, value) in [("Log", 0.0), ("Log", -1.0), ("Log10", 0.0), ("Sqrt", -4.0)] {
+            assert!(matches!(run(op, json!({"in": value})), Err(Error::OperationError { .. })), "{}({})", op, value);
+        }
+    }
+
+    #[test]
+    fn make_object_zips_keys_and_values() {
+        assert_eq!(run("MakeObject", json!({"keys": ["a", "b"], "values": [1, 2]})).unwrap()["out"], json!({"a": 1, "b": 2}));
+    }
+
+    #[test]
+    fn make_object_rejects_mismatched_lengths_and_bad_keys() {
+        assert!(matches!(run("MakeObject", json!({"keys": ["a", "b"], "values": [1]})), Err(Error::OperationError { .. })));
+        assert!(matches!(run("MakeObject", json!({"keys": [1], "values": [1]})), Err(Error::InvalidType { .. })));
+    }
+
+    #[test]
+    fn match_picks_the_case_or_default() {
+        let cases: Value = json!({"1": "one", "red": "stop"});
+        assert_eq!(run("Match", json!({"value": 1.0, "cases": cases, "default": "other"})).unwrap()["out"], json!("one"));
+        assert_eq!(run("Match", json!({"value": "red", "cases": cases})).unwrap()["out"], json!("stop"));
+        assert_eq!(run("Match", json!({"value": "blue", "cases": cases, "default": "other"})).unwrap()["out"], json!("other"));
+    }
+
+    #[test]
+    fn match_without_case_or_default_fails() {
+        let err: String = reason(run("Match", json!({"value": "blue", "cases": {"red": 1}})));
+        assert!(err.contains("No case matches 'blue'"), "{}", err);
+    }
+
+    #[test]
+    fn match_keys_large_whole_floats_like_integers() {
+        let cases: Value = json!({ "10000000000000000": "big", "0": "zero", "2.5": "half" });
+        let float: Value = serde_json::from_str("1e16").unwrap();
+        assert_eq!(run("Match", json!({"value": float, "cases": cases})).unwrap()["out"], json!("big"));
+        assert_eq!(run("Match", json!({"value": 10000000000000000_i64, "cases": cases})).unwrap()["out"], json!("big"));
+        assert_eq!(run("Match", json!({"value": -0.0, "cases": cases})).unwrap()["out"], json!("zero"));
+        assert_eq!(run("Match", json!({"value": 2.5, "cases": cases})).unwrap()["out"], json!("half"));
+    }
+
+    #[test]
+    fn div_safe_divides_normally() {
+        assert_eq!(run("DivSafe", json!({"a": 9, "b": 2, "fallback": 0})).unwrap()["out"], json!(4.5));
+    }
+
+    #[test]
+    fn div_safe_recovers_from_zero() {
+        assert_eq!(run("DivSafe", json!({"a": 1, "b": 0, "fallback": -1})).unwrap()["out"], json!(-1));
+        assert_eq!(run("DivSafe", json!({"a": 1, "b": 0})).unwrap()["out"], Value::Null);
+        assert!(run("Div", json!({"a": 1, "b": 0})).is_err());
+    }
+
+    #[test]
+    fn integer_and_float_are_equal_everywhere() {
+        assert!(values_equal(&json!(1), &json!(1.0)));
+        assert!(values_equal(&json!({"a": [1]}), &json!({"a": [1.0]})));
+        assert!(!values_equal(&json!(1), &json!("1")));
+        assert_eq!(run("Eq", json!({"a": 1, "b": 1.0})).unwrap()["out"], json!(true));
+        assert_eq!(run("Union", json!({"a": [1], "b": [1.0, 2]})).unwrap()["out"], json!([1, 2]));
+        assert_eq!(run("Intersect", json!({"a": [1, 3], "b": [1.0]})).unwrap()["out"], json!([1]));
+        assert_eq!(run("Difference", json!({"a": [1, 3], "b": [1.0]})).unwrap()["out"], json!([3]));
+    }
+
+    #[test]
+    fn approx_eq_tolerates_rounding() {
+        let sum: f64 = 0.1 + 0.2;
+        assert_eq!(run("Eq", json!({"a": sum, "b": 0.3})).unwrap()["out"], json!(false));
+        assert_eq!(run("ApproxEq", json!({"a": sum, "b": 0.3})).unwrap()["out"], json!(true));
+    }
+
+    #[test]
+    fn approx_eq_respects_epsilon() {
+        assert_eq!(run("ApproxEq", json!({"a": 1.0, "b": 1.001})).unwrap()["out"], json!(false));
+        assert_eq!(run("ApproxEq", json!({"a": 1.0, "b": 1.001, "eps": 0.01})).unwrap()["out"], json!(true));
+    }
+
+    #[test]
+    fn scan_emits_running_totals() {
+        let totals: Value = run("Scan", json!({"list": [1, 2, 3], "apply_op": "Add", "initial": 0, "acc_arg": "a", "item_arg": "b"}))
+            .unwrap().remove("out").unwrap();
+        assert_eq!(totals, json!([0, 1, 3, 6]));
+    }
+
+    #[test]
+    fn route_splits_a_list_by_predicate() {
+        let res: HashMap<String, Value> = run("Route", json!({"list": [1, 5, 2, 8], "apply_op": "Gt", "arg": "a", "params": {"b": 3}})).unwrap();
+        assert_eq!(res["true"], json!([5, 8]));
+        assert_eq!(res["false"], json!([1, 2]));
+        assert_eq!(res["out"], json!([1, 5, 2, 8]));
+    }
+
+    #[test]
+    fn sample_is_reproducible_with_a_seed() {
+        let inputs: Value = json!({"list": [1, 2, 3, 4, 5, 6, 7, 8], "n": 3, "seed": 42});
+        let first: Value = run("Sample", inputs.clone()).unwrap().remove("out").unwrap();
+        assert_eq!(first.as_array().unwrap().len(), 3);
+        assert_eq!(run("Sample", inputs).unwrap()["out"], first);
+        let mut seen: Vec<i64> = first.as_array().unwrap().iter().map(|v: &Value| v.as_i64().unwrap()).collect();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), 3);
+    }
+
+    #[test]
+    fn sample_larger_than_list_returns_everything() {
+        assert_eq!(run("Sample", json!({"list": [1, 2, 3], "n": 10})).unwrap()["out"], json!([1, 2, 3]));
+        let mut shuffled: Vec<Value> = run("Sample", json!({"list": [1, 2, 3], "n": 10, "seed": 7})).unwrap()["out"]
+            .as_array().unwrap().clone();
+        shuffled.sort_by_key(|v: &Value| v.as_i64());
+        assert_eq!(shuffled, vec![json!(1), json!(2), json!(3)]);
+        assert!(!Ops::default().get("Sample").unwrap().is_cacheable(&HashMap::new()));
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        let encoded: Value = run("Base64Encode", json!({"in": "héllo, spell"})).unwrap().remove("out").unwrap();
+        assert_eq!(encoded, json!("aMOpbGxvLCBzcGVsbA=="));
+        assert_eq!(run("Base64Decode", json!({"in": encoded})).unwrap()["out"], json!("héllo, spell"));
+    }
+
+    #[test]
+    fn base64_rejects_malformed_or_binary_input() {
+        assert!(reason(run("Base64Decode", json!({"in": "not base64!"}))).starts_with("Invalid base64"));
+        assert!(reason(run("Base64Decode", json!({"in": "/w=="}))).contains("not valid UTF-8"));
+    }
+
+    #[test]
+    fn dates_parse_and_format_back() {
+        let ts: Value = run("ParseDate", json!({"in": "2024-03-01T12:30:00Z"})).unwrap().remove("out").unwrap();
+        assert_eq!(ts, json!(1_709_296_200));
+        assert_eq!(run("FormatDate", json!({"in": ts})).unwrap()["out"], json!("2024-03-01T12:30:00Z"));
+        let day: Value = run("ParseDate", json!({"in": "01/03/2024", "format": "%d/%m/%Y"})).unwrap().remove("out").unwrap();
+        assert_eq!(run("FormatDate", json!({"in": day, "format": "%Y-%m-%d"})).unwrap()["out"], json!("2024-03-01"));
+    }
+
+    #[test]
+    fn malformed_dates_name_the_input() {
+        let err: String = reason(run("ParseDate", json!({"in": "yesterday"})));
+        assert!(err.contains("'yesterday'"), "{}", err);
+        assert!(reason(run("FormatDate", json!({"in": 0, "format": "%Q"}))).contains("Invalid date format"));
+    }
+
+    #[test]
+    fn large_integers_compare_exactly() {
+        let big: Value = json!(9_007_199_254_740_993_i64); // 2^53 + 1
+        assert_eq!(run("Eq", json!({"a": big, "b": 9_007_199_254_740_992_i64})).unwrap()["out"], json!(false));
+        assert_eq!(run("Gt", json!({"a": big, "b": 9_007_199_254_740_992_i64})).unwrap()["out"], json!(true));
+        assert_eq!(run("Lt", json!({"a": -1, "b": u64::MAX})).unwrap()["out"], json!(true));
+        assert_eq!(run("Eq", json!({"a": -0.0, "b": 0})).unwrap()["out"], json!(true));
+        assert_eq!(run("Eq", json!({"a": u64::MAX, "b": 0})).unwrap()["out"], json!(false));
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        assert_eq!(run("Transpose", json!({"list": [[1, 2], [3, 4]]})).unwrap()["out"], json!([[1, 3], [2, 4]]));
+        assert_eq!(run("Transpose", json!({"list": [[1, 2, 3]]})).unwrap()["out"], json!([[1], [2], [3]]));
+        assert_eq!(run("Transpose", json!({"list": []})).unwrap()["out"], json!([]));
+    }
+
+    #[test]
+    fn transpose_rejects_ragged_rows() {
+        let err: String = reason(run("Transpose", json!({"list": [[1, 2], [3]]})));
+        assert!(err.contains("row 1 has 1"), "{}", err);
+    }
+
+    #[test]
+    fn pipe_threads_each_stage_into_the_next() {
+        let stages: Value = json!([
+            { "op": "Cast", "params": { "to": "Integer" } },
+            { "op": "Add", "arg": "a", "params": { "b": 1 } },
+            { "op": "Cast", "params": { "to": "String" } }
+        ]);
+        assert_eq!(run("Pipe", json!({"in": "41", "ops": stages})).unwrap()["out"], json!("42"));
+    }
+
+    #[test]
+    fn pipe_failures_name_the_stage() {
+        let stages: Value = json!([
+            { "op": "Add", "arg": "a", "params": { "b": 1 } },
+            { "op": "Div", "arg": "a", "params": { "b": 0 } }
+        ]);
+        let err: String = reason(run("Pipe", json!({"in": 1, "ops": stages})));
+        assert!(err.starts_with("Pipe stage 1:"), "{}", err);
+    }
+
+    #[test]
+    fn render_fills_the_template_per_record() {
+        let records: Value = json!([{"name": "ada", "score": 9.5}, {"name": "bob", "score": 7}]);
+        let report: Value = run("Render", json!({"list": records, "template": "{{name}}: {{score}}"})).unwrap().remove("out").unwrap();
+        assert_eq!(report, json!("ada: 9.5\nbob: 7"));
+        let joined: Value = run("Render", json!({"list": records, "template": "{{name}}", "sep": ", "})).unwrap().remove("out").unwrap();
+        assert_eq!(joined, json!("ada, bob"));
+    }
+
+    #[test]
+    fn render_names_missing_fields() {
+        let err: String = reason(run("Render", json!({"list": [{"name": "ada"}, {}], "template": "{{name}}"})));
+        assert_eq!(err, "Element 1 has no field 'name'");
+    }
+
+    #[test]
+    fn set_ops_keep_first_occurrence_order() {
+        let (a, b): (Value, Value) = (json!([3, 1, 3, 2]), json!([2, 4, 4, 3]));
+        assert_eq!(run("Union", json!({"a": a, "b": b})).unwrap()["out"], json!([3, 1, 2, 4]));
+        assert_eq!(run("Intersect", json!({"a": a, "b": b})).unwrap()["out"], json!([3, 2]));
+        assert_eq!(run("Difference", json!({"a": a, "b": b})).unwrap()["out"], json!([1]));
+        assert_eq!(run("Union", json!({"a": [], "b": []})).unwrap()["out"], json!([]));
+    }
+
+    #[test]
+    fn pretty_json_indents_objects() {
+        let text: Value = run("PrettyJson", json!({"in": {"a": [1]}, "indent": 2})).unwrap().remove("out").unwrap();
+        assert_eq!(text, json!("{\n  \"a\": [\n    1\n  ]\n}"));
+    }
+
+    #[test]
+    fn pretty_json_reparses_strings_on_request() {
+        let raw: &str = "{\"a\":1}";
+        assert_eq!(run("PrettyJson", json!({"in": raw, "indent": 1, "reparse": true})).unwrap()["out"], json!("{\n \"a\": 1\n}"));
+        assert_eq!(run("PrettyJson", json!({"in": raw})).unwrap()["out"], json!("\"{\\\"a\\\":1}\""));
+        assert!(reason(run("PrettyJson", json!({"in": "{", "reparse": true}))).contains("JSON"));
+    }
+
+    #[test]
+    fn pretty_json_rejects_oversized_indent() {
+        assert_eq!(run("PrettyJson", json!({"in": [1], "indent": 16})).unwrap()["out"], json!(format!("[\n{}1\n]", " ".repeat(16))));
+        let err: String = reason(run("PrettyJson", json!({"in": [1], "indent": 17})));
+        assert!(err.contains("at most 16"), "{}", err);
+        let huge: Value = serde_json::from_str("1e300").unwrap();
+        assert!(reason(run("PrettyJson", json!({"in": [1], "indent": huge}))).contains("at most 16"));
+    }
+
+    #[test]
+    fn map_rejects_mistyped_params_before_iterating() {
+        let err: String = reason(run("Map", json!({"list": ["first element fails too"], "apply_op": "Add", "arg": "a", "params": {"b": "x"}})));
+        assert_eq!(err, "param 'b' for Add must be Number, got String \"x\"");
+        assert!(reason(run("Filter", json!({"list": [1], "apply_op": "Gt", "arg": "a", "params": {"b": [1]}}))).starts_with("param 'b'"));
+    }
+
+    #[test]
+    fn pipe_checks_stage_params_before_running() {
+        // Stage 0 would fail on division by zero if it ran
+        let stages: Value = json!([
+            { "op": "Div", "arg": "a", "params": { "b": 0 } },
+            { "op": "Add", "arg": "a", "params": { "b": "one" } }
+        ]);
+        let err: String = reason(run("Pipe", json!({"in": 1, "ops": stages})));
+        assert!(err.starts_with("Pipe stage 1: param 'b' for Add must be Number"), "{}", err);
+    }
+
+    #[test]
+    fn arg_extrema_return_first_index() {
+        assert_eq!(run("ArgMax", json!({"list": [3, 7, 2]})).unwrap()["out"], json!(1));
+        assert_eq!(run("ArgMin", json!({"list": [3, 7, 2]})).unwrap()["out"], json!(2));
+        assert_eq!(run("ArgMax", json!({"list": [5, 1, 5]})).unwrap()["out"], json!(0));
+        assert_eq!(run("ArgMin", json!({"list": [4, 1, 1.0]})).unwrap()["out"], json!(1));
+    }
+
+    #[test]
+    fn arg_extrema_reject_empty_lists() {
+        assert_eq!(reason(run("ArgMax", json!({"list": []}))), "Cannot take the extremum of an empty list");
+    }
+
+    #[test]
+    fn regex_replace_substitutes_every_match() {
+        assert_eq!(run("RegexReplace", json!({"in": "a1b22c", "pattern": "[0-9]+", "replacement": "#"})).unwrap()["out"], json!("a#b#c"));
+        assert_eq!(
+            run("RegexReplace", json!({"in": "2024-03-01", "pattern": "(\\d+)-(\\d+)-(\\d+)", "replacement": "$3/$2/$1"})).unwrap()["out"],
+            json!("01/03/2024")
+        );
+    }
+
+    #[test]
+    fn regex_replace_rejects_invalid_patterns() {
+        assert!(reason(run("RegexReplace", json!({"in": "x", "pattern": "(", "replacement": ""}))).starts_with("Invalid regex pattern"));
+    }
+
+    #[test]
+    fn map_if_doubles_only_matching_elements() {
+        let doubled: Value = run("MapIf", json!({
+            "list": [1, 2, 3, 4],
+            "predicate_op": "Match", "predicate_arg": "value",
+            "predicate_params": {"cases": {"2": true, "4": true}, "default": false},
+            "map_op": "Mul", "map_arg": "a", "map_params": {"b": 2}
+        })).unwrap().remove("out").unwrap();
+        assert_eq!(doubled, json!([1, 4, 3, 8]));
+    }
+
+    #[test]
+    fn map_if_treats_non_boolean_predicates_as_false() {
+        let res: Value = run("MapIf", json!({
+            "list": [1, "two"],
+            "predicate_op": "Coalesce", "predicate_arg": "a",
+            "map_op": "Mul", "map_arg": "a", "map_params": {"b": 2}
+        })).unwrap().remove("out").unwrap();
+        assert_eq!(res, json!([1, "two"]));
+    }
+
+    #[test]
+    fn deep_merge_recurses_into_objects() {
+        let merged: Value = run("DeepMerge", json!({
+            "a": {"db": {"host": "localhost", "port": 5432}, "debug": false},
+            "b": {"db": {"port": 6543}, "debug": true}
+        })).unwrap().remove("out").unwrap();
+        assert_eq!(merged, json!({"db": {"host": "localhost", "port": 6543}, "debug": true}));
+    }
+
+    #[test]
+    fn deep_merge_replaces_arrays_unless_asked_to_concat() {
+        let (a, b): (Value, Value) = (json!({"tags": [1, 2], "x": {"y": 1}}), json!({"tags": [3], "x": 5}));
+        assert_eq!(run("DeepMerge", json!({"a": a, "b": b})).unwrap()["out"], json!({"tags": [3], "x": 5}));
+        assert_eq!(
+            run("DeepMerge", json!({"a": a, "b": b, "concat_arrays": true})).unwrap()["out"],
+            json!({"tags": [1, 2, 3], "x": 5})
+        );
+    }
+
+    #[test]
+    fn median_interpolates_even_lengths() {
+        assert_eq!(run("Median", json!({"list": [5, 1, 3]})).unwrap()["out"], json!(3.0));
+        assert_eq!(run("Median", json!({"list": [4, 1, 3, 2]})).unwrap()["out"], json!(2.5));
+        assert_eq!(run("Percentile", json!({"list": [10, 20, 30, 40, 50], "p": 90})).unwrap()["out"], json!(46.0));
+        assert_eq!(run("Percentile", json!({"list": [7], "p": 0})).unwrap()["out"], json!(7.0));
+    }
+
+    #[test]
+    fn percentile_rejects_empty_lists_and_bad_ranks() {
+        assert!(run("Median", json!({"list": []})).is_err());
+        assert!(run("Percentile", json!({"list": [1, 2], "p": 101})).is_err());
+        assert!(run("Percentile", json!({"list": [1, 2], "p": -1})).is_err());
+    }
+
+    #[test]
+    fn escape_json_and_url() {
+        assert_eq!(run("Escape", json!({"in": "say \"hi\"\n", "mode": "json"})).unwrap()["out"], json!("say \\\"hi\\\"\\n"));
+        assert_eq!(run("Escape", json!({"in": "a b&c", "mode": "url"})).unwrap()["out"], json!("a%20b%26c"));
+        assert_eq!(run("Escape", json!({"in": "it's", "mode": "shell"})).unwrap()["out"], json!("'it'\\''s'"));
+    }
+
+    #[test]
+    fn unescape_inverts_escape() {
+        for mode in ["json", "url", "shell"] {
+            let text: &str = "mixed 'quotes' \"and\" spaces/é";
+            let escaped: Value = run("Escape", json!({"in": text, "mode": mode})).unwrap().remove("out").unwrap();
+            assert_eq!(run("Unescape", json!({"in": escaped, "mode": mode})).unwrap()["out"], json!(text), "{}", mode);
+        }
+        assert!(reason(run("Escape", json!({"in": "x", "mode": "html"}))).starts_with("Unsupported escape mode 'html'"));
+    }
+
+    #[test]
+    fn url_unescape_rejects_malformed_escapes() {
+        let unescape = |text: &str| -> String { reason(run("Unescape", json!({"in": text, "mode": "url"}))) };
+        assert_eq!(unescape("a%+5"), "Cannot unescape url text: invalid escape '%+5' at byte 1");
+        assert_eq!(unescape("%zz"), "Cannot unescape url text: invalid escape '%zz' at byte 0");
+        assert_eq!(unescape("%éa"), "Cannot unescape url text: invalid escape '%éa' at byte 0");
+        assert_eq!(unescape("x%4"), "Cannot unescape url text: truncated escape at byte 1");
+        assert_eq!(unescape("%"), "Cannot unescape url text: truncated escape at byte 0");
+        assert_eq!(unescape("%C3"), "Cannot unescape url text: not valid UTF-8: incomplete utf-8 byte sequence from index 0");
+        assert_eq!(run("Unescape", json!({"in": "%C3%A9%2b", "mode": "url"})).unwrap()["out"], json!("é+"));
+    }
+
+    #[test]
+    fn normalize_scales_into_unit_range() {
+        assert_eq!(run("Normalize", json!({"list": [2, 4, 6]})).unwrap()["out"], json!([0.0, 0.5, 1.0]));
+        assert_eq!(run("Normalize", json!({"list": [3, 3]})).unwrap()["out"], json!([0.0, 0.0]));
+    }
+
+    #[test]
+    fn normalize_scales_into_a_custom_range() {
+        assert_eq!(run("Normalize", json!({"list": [0, 5, 10], "lo": -1, "hi": 1})).unwrap()["out"], json!([-1.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn to_csv_writes_a_header_and_rows() {
+        let csv: Value = run("ToCsv", json!({"list": [{"a": 1, "b": "x"}, {"a": 2, "b": "y"}]})).unwrap().remove("out").unwrap();
+        assert_eq!(csv, json!("a,b\n1,x\n2,y\n"));
+    }
+
+    #[test]
+    fn to_csv_quotes_fields_and_orders_columns() {
+        let list: Value = json!([{"name": "Smith, J", "note": "said \"hi\"", "id": 1}]);
+        let csv: Value = run("ToCsv", json!({"list": list, "columns": ["id", "name", "note"]})).unwrap().remove("out").unwrap();
+        assert_eq!(csv, json!("id,name,note\n1,\"Smith, J\",\"said \"\"hi\"\"\"\n"));
+        assert!(matches!(run("ToCsv", json!({"list": [1]})), Err(Error::InvalidType { .. })));
+    }
+
+    #[test]
+    fn parse_csv_round_trips_with_to_csv() {
+        let list: Value = json!([{"name": "Smith, J", "note": "line\nbreak"}, {"name": "x", "note": "say \"hi\""}]);
+        let csv: Value = run("ToCsv", json!({"list": list})).unwrap().remove("out").unwrap();
+        assert_eq!(run("ParseCsv", json!({"in": csv})).unwrap()["out"], list);
+    }
+
+    #[test]
+    fn parse_csv_without_header_and_ragged_rows() {
+        assert_eq!(run("ParseCsv", json!({"in": "a,\"b,c\"\n1,2\n", "header": false})).unwrap()["out"], json!([["a", "b,c"], ["1", "2"]]));
+        let err: String = reason(run("ParseCsv", json!({"in": "a,b\n1,2\n3\n"})));
+        assert!(err.starts_with("Record 3 has 1 fields"), "{}", err);
+    }
+
+    #[test]
+    fn validate_passes_finite_lists_through() {
+        assert_eq!(run("Validate", json!({"list": [1, 2.5, -3]})).unwrap()["out"], json!([1, 2.5, -3]));
+    }
+
+    #[test]
+    fn validate_names_the_non_finite_index() {
+        let list: Value = Value::Array(vec![json!(1), json!(2), Value::from(f64::INFINITY)]);
+        // serde_json stores non-finite floats as null
+        assert_eq!(reason(run("Validate", json!({"list": list}))), "Element 2 is not a finite number: null");
+    }
+
+    #[test]
+    fn pick_and_omit_project_keys() {
+        let obj: Value = json!({"a": 1, "b": 2, "c": 3});
+        assert_eq!(run("Pick", json!({"obj": obj, "keys": ["a", "c", "zzz"]})).unwrap()["out"], json!({"a": 1, "c": 3}));
+        assert_eq!(run("Omit", json!({"obj": obj, "keys": ["b"]})).unwrap()["out"], json!({"a": 1, "c": 3}));
+    }
+
+    #[test]
+    fn pick_requires_an_object() {
+        assert!(matches!(run("Pick", json!({"obj": [1], "keys": ["a"]})), Err(Error::InvalidType { .. })));
+    }
+
+    #[test]
+    fn repeat_builds_constant_arrays() {
+        assert_eq!(run("Repeat", json!({"value": 7, "count": 3})).unwrap()["out"], json!([7, 7, 7]));
+        assert_eq!(run("Repeat", json!({"value": {"a": 1}, "count": 0})).unwrap()["out"], json!([]));
+    }
+
+    #[test]
+    fn repeat_rejects_negative_counts() {
+        assert_eq!(reason(run("Repeat", json!({"value": 1, "count": -1}))), "Repeat count must be a non-negative integer, got -1");
+    }
+
+    #[test]
+    fn repeat_rejects_counts_beyond_the_cap() {
+        let huge: Value = serde_json::from_str("1e300").unwrap();
+        let err: String = reason(run("Repeat", json!({"value": 0, "count": huge})));
+        assert!(err.contains("at most 1000000"), "{}", err);
+        assert!(reason(run("Repeat", json!({"value": 0, "count": 1_000_001}))).contains("at most"));
+        let out: Value = run("Repeat", json!({"value": 0, "count": 1_000_000})).unwrap().remove("out").unwrap();
+        assert_eq!(out.as_array().map(Vec::len), Some(1_000_000));
+    }
+
+    #[test]
+    fn hash_ignores_key_order() {
+        let first: Value = run("Hash", json!({"in": {"a": 1, "b": [1, 2]}})).unwrap().remove("out").unwrap();
+        let second: Value = run("Hash", json!({"in": {"b": [1, 2], "a": 1}})).unwrap().remove("out").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.as_str().unwrap().len(), 64);
+        assert_ne!(run("Hash", json!({"in": {"a": 2, "b": [1, 2]}})).unwrap()["out"], first);
+        assert_eq!(run("Hash", json!({"in": "x", "algo": "sha512"})).unwrap()["out"].as_str().unwrap().len(), 128);
+    }
+
+    #[test]
+    fn hash_agrees_with_values_equal() {
+        let hash = |value: Value| -> Value { run("Hash", json!({"in": value})).unwrap().remove("out").unwrap() };
+        let pairs: Vec<(Value, Value)> = vec![
+            (json!(1), json!(1.0)),
+            (json!(10000000000000000_i64), serde_json::from_str("1e16").unwrap()),
+            (json!(0), json!(-0.0)),
+            (json!(18446744073709551615_u64), json!(18446744073709551615_u64)),
+            (json!({"a": [1, 2.0], "b": {"c": 3}}), json!({"b": {"c": 3.0}, "a": [1.0, 2]})),
+        ];
+        for (a, b) in pairs {
+            assert!(values_equal(&a, &b), "{} != {}", a, b);
+            assert_eq!(hash(a.clone()), hash(b.clone()), "{} and {} hash differently", a, b);
+        }
+        assert_ne!(hash(json!(1)), hash(json!(1.5)));
+    }
+
+    #[test]
+    fn interleave_round_robins_uneven_lists() {
+        assert_eq!(run("Interleave", json!({"lists": [[1, 4], [2, 5, 6], [3]]})).unwrap()["out"], json!([1, 2, 3, 4, 5, 6]));
+        assert_eq!(run("Interleave", json!({"lists": [[], [1], []]})).unwrap()["out"], json!([1]));
+        assert_eq!(run("Interleave", json!({"lists": []})).unwrap()["out"], json!([]));
+    }
+}
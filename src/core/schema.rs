@@ -3,41 +3,820 @@
 // Copyright (c) 2025 Santino Research. MIT License.
 // ─────────────────────────────────────────────────────────────────────────────
 
+use serde::de::{self, Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use super::types::{SpellType, TypedValue};
 use super::error::{Error, Result};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Graph {
     #[serde(flatten)]
     pub nodes: HashMap<String, Node>,
+
+    /// Where each node is defined in the source, when parsed from text.
+    #[serde(skip)]
+    pub source_map: SourceMap,
+
+    /// Files listed by a top-level `include` directive, not yet merged in.
+    /// See [`Graph::resolve_includes`].
+    #[serde(rename = "include", skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<String>,
+}
+
+/// A 1-based line and column in graph source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Source positions of node definitions: the node's key in the object form,
+/// or the opening brace of its entry in the array form. Nodes merged in by
+/// `include` keep their position in, and the path of, the file defining them.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    spans: HashMap<String, Span>,
+    files: HashMap<String, String>,
+}
+
+impl SourceMap {
+    /// Scans graph source (comments allowed) for node definitions.
+    /// Malformed source yields whatever was found before the problem.
+    pub fn from_source(src: &str) -> SourceMap {
+        let chars: Vec<char> = strip_comments(src).chars().collect();
+        let mut spans: HashMap<String, Span> = HashMap::new();
+        let mut depth: usize = 0;
+        let mut pos: Span = Span { line: 1, column: 1 };
+        // Start of the array-form entry being scanned, and whether its `id` value comes next
+        let mut listed: bool = false;
+        let mut entry: Option<Span> = None;
+        let mut expect_id: bool = false;
+        let mut i: usize = 0;
+
+        while i < chars.len() {
+            let start: Span = pos;
+            match chars[i] {
+                '"' => {
+                    let mut text: String = String::new();
+                    let mut escaped: bool = false;
+                    i += 1;
+                    pos.column += 1;
+                    while i < chars.len() && (escaped || chars[i] != '"') {
+                        escaped = !escaped && chars[i] == '\\';
+                        text.push(chars[i]);
+                        i += 1;
+                        pos.column += 1;
+                    }
+                    let text: String = serde_json::from_str(&format!("\"{}\"", text)).unwrap_or(text);
+                    let is_key: bool = chars[(i + 1).min(chars.len())..].iter()
+                        .find(|c: &&char| !c.is_whitespace())
+                        == Some(&':');
+
+                    if is_key && depth == 1 {
+                        let _: Option<Span> = spans.insert(text, start);
+                    } else if is_key && depth == 3 && listed {
+                        expect_id = text == "id";
+                    } else if expect_id {
+                        if let Some(span) = entry {
+                            let _: Option<Span> = spans.insert(text, span);
+                        }
+                        expect_id = false;
+                    }
+                }
+                '{' | '[' => {
+                    depth += 1;
+                    if depth == 2 {
+                        listed = chars[i] == '[';
+                    }
+                    if depth == 3 && chars[i] == '{' {
+                        entry = Some(start);
+                    }
+                }
+                '}' | ']' => depth = depth.saturating_sub(1),
+                '\n' => {
+                    pos.line += 1;
+                    pos.column = 0;
+                }
+                _ => {}
+            }
+            i += 1;
+            pos.column += 1;
+        }
+
+        SourceMap { spans, files: HashMap::new() }
+    }
+
+    /// Gives nodes hoisted from inline operations, named `<node>.<port>`, the
+    /// position of the node they came from.
+    fn cover_inline_ops(&mut self, nodes: &HashMap<String, Node>) {
+        for id in nodes.keys() {
+            if self.spans.contains_key(id) {
+                continue;
+            }
+            let mut host: &str = id;
+            while let Some(end) = host.rfind('.') {
+                host = &host[..end];
+                if let (true, Some(span)) = (nodes.contains_key(host), self.spans.get(host).copied()) {
+                    let _: Option<Span> = self.spans.insert(id.clone(), span);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Position of a node's definition. Nodes hoisted from inline operations,
+    /// named `<node>.<port>`, report the position of the node they came from.
+    pub fn get(&self, node: &str) -> Option<Span> {
+        self.spans.get(node).copied()
+    }
+
+    /// Path of the included file that defines a node, or `None` for nodes of
+    /// the graph's own source.
+    pub fn file(&self, node: &str) -> Option<&str> {
+        self.files.get(node).map(String::as_str)
+    }
+}
+
+/// A node in the array graph form, carrying its own id.
+#[derive(Deserialize)]
+struct ListedNode {
+    id: String,
+    #[serde(flatten)]
+    node: Node,
+}
+
+/// Deserializes a graph, rejecting duplicate node ids.
+/// JSON permits repeated keys, and a plain map would silently keep the last one.
+///
+/// Two forms are accepted: an object keyed by node id, or
+/// `{ "nodes": [ { "id": "a", "op": ... }, ... ] }`.
+impl<'de> Deserialize<'de> for Graph {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct GraphVisitor;
+
+        impl<'de> Visitor<'de> for GraphVisitor {
+            type Value = Graph;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "an object mapping node ids to nodes")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Graph, A::Error> {
+                let mut nodes: HashMap<String, Node> = HashMap::new();
+                let mut listed: Option<Vec<ListedNode>> = None;
+                let mut includes: Vec<String> = Vec::new();
+
+                while let Some(id) = map.next_key::<String>()? {
+                    if nodes.contains_key(&id) {
+                        return Err(de::Error::custom(format!("duplicate node id: '{}'", id)));
+                    }
+
+                    // `nodes` or `include` holding an array is a directive; otherwise it is an ordinary node id
+                    let node: Node = if id == "include" {
+                        match map.next_value::<serde_json::Value>()? {
+                            serde_json::Value::Array(paths) => {
+                                includes = serde_json::from_value(serde_json::Value::Array(paths))
+                                    .map_err(|_| de::Error::custom("'include' must list file paths"))?;
+                                continue;
+                            }
+                            other => serde_json::from_value(other).map_err(de::Error::custom)?,
+                        }
+                    } else if id == "nodes" {
+                        match map.next_value::<serde_json::Value>()? {
+                            serde_json::Value::Array(items) => {
+                                let items: Vec<ListedNode> = serde_json::from_value(serde_json::Value::Array(items))
+                                    .map_err(de::Error::custom)?;
+                                listed = Some(items);
+                                continue;
+                            }
+                            other => serde_json::from_value(other).map_err(de::Error::custom)?,
+                        }
+                    } else {
+                        map.next_value()?
+                    };
+                    let _: Option<Node> = nodes.insert(id, node);
+                }
+
+                if let Some(items) = listed {
+                    if !nodes.is_empty() {
+                        return Err(de::Error::custom("array-form graph may only contain 'nodes' and 'include' keys"));
+                    }
+                    for item in items {
+                        if nodes.contains_key(&item.id) {
+                            return Err(de::Error::custom(format!("duplicate node id: '{}'", item.id)));
+                        }
+                        let _: Option<Node> = nodes.insert(item.id, item.node);
+                    }
+                }
+
+                Graph::hoist_inline_ops(&mut nodes).map_err(de::Error::custom)?;
+                Ok(Graph { nodes, source_map: SourceMap::default(), includes })
+            }
+        }
+
+        deserializer.deserialize_map(GraphVisitor)
+    }
+}
+
+impl Graph {
+    /// Turns inline operation arguments, `{ "op": ..., <args>, "type": T }`,
+    /// into nodes of their own named `<node>.<port>`, replacing each with a
+    /// reference of type `T`. Inline operations may nest.
+    fn hoist_inline_ops(nodes: &mut HashMap<String, Node>) -> std::result::Result<(), String> {
+        let mut pending: Vec<String> = nodes.keys().cloned().collect();
+        pending.sort();
+
+        while let Some(id) = pending.pop() {
+            let mut ports: Vec<String> = nodes[&id].args.iter()
+                .filter(|(_, value): &(&String, &serde_json::Value)| Node::is_inline_op(value))
+                .map(|(port, _): (&String, &serde_json::Value)| port.clone())
+                .collect();
+            ports.sort();
+
+            for port in ports {
+                let Some(serde_json::Value::Object(mut inline)) = nodes.get_mut(&id).and_then(|n: &mut Node| n.args.remove(&port)) else { continue };
+                let value_type: serde_json::Value = inline.remove("type").unwrap_or(serde_json::Value::Null);
+                let inline_id: String = format!("{}.{}", id, port);
+                if nodes.contains_key(&inline_id) {
+                    return Err(format!("inline operation in node '{}' port '{}' would replace existing node '{}'", id, port, inline_id));
+                }
+
+                let node: Node = serde_json::from_value(serde_json::Value::Object(inline))
+                    .map_err(|e: serde_json::Error| format!("inline operation in node '{}' port '{}': {}", id, port, e))?;
+                let _: Option<Node> = nodes.insert(inline_id.clone(), node);
+                let reference: serde_json::Value = serde_json::json!({ "ref": inline_id, "type": value_type });
+                if let Some(n) = nodes.get_mut(&id) {
+                    let _: Option<serde_json::Value> = n.args.insert(port, reference);
+                }
+                pending.push(inline_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a graph from JSON source, allowing `//` and `/* */` comments,
+    /// and checks its type annotations.
+    pub fn from_json_str(src: &str) -> Result<Graph> {
+        let mut graph: Graph = serde_json::from_str(&strip_comments(src))
+            .map_err(|e: serde_json::Error| -> Error {
+                // serde_json appends the position to its message; it is reported separately
+                let message: String = e.to_string();
+                let suffix: String = format!(" at line {} column {}", e.line(), e.column());
+                Error::Parse {
+                    line: e.line(),
+                    column: e.column(),
+                    reason: message.strip_suffix(&suffix).unwrap_or(&message).to_string(),
+                }
+            })?;
+        graph.source_map = SourceMap::from_source(src);
+        graph.source_map.cover_inline_ops(&graph.nodes);
+        graph.check_types()?;
+        Ok(graph)
+    }
+
+    /// Merges the graphs named by `include` into this one, which was read
+    /// from `path`. Included paths are relative to the including file, and
+    /// an included file's nodes are renamed `<file stem>.<id>`, so `helper.json`
+    /// contributes `helper.sum` for its node `sum`. Includes may nest. A renamed
+    /// node that collides with an existing one, including a node hoisted from an
+    /// inline operation (`helper` with inline port `sum`), is an error.
+    pub fn resolve_includes(&mut self, path: &Path) -> Result<()> {
+        let mut chain: Vec<PathBuf> = Vec::new();
+        self.merge_includes(path, &mut chain)
+    }
+
+    fn merge_includes(&mut self, path: &Path, chain: &mut Vec<PathBuf>) -> Result<()> {
+        let canonical: PathBuf = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if chain.contains(&canonical) {
+            let cycle: Vec<String> = chain.iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p: &PathBuf| p.display().to_string())
+                .collect();
+            return Err(Error::Include {
+                path: path.display().to_string(),
+                reason: format!("include cycle: {}", cycle.join(" -> ")),
+            });
+        }
+        chain.push(canonical);
+
+        let dir: &Path = path.parent().unwrap_or_else(|| Path::new(""));
+        for include in std::mem::take(&mut self.includes) {
+            let include_path: PathBuf = dir.join(&include);
+            let fail = |reason: String| Error::Include { path: include_path.display().to_string(), reason };
+
+            let src: String = fs::read_to_string(&include_path).map_err(|e: std::io::Error| fail(e.to_string()))?;
+            let mut included: Graph = Graph::from_json_str(&src).map_err(|e: Error| fail(e.to_string()))?;
+            included.merge_includes(&include_path, chain)?;
+
+            let namespace: String = include_path.file_stem()
+                .map(|stem: &std::ffi::OsStr| stem.to_string_lossy().into_owned())
+                .ok_or_else(|| fail("path has no file name".to_string()))?;
+            let file: String = include_path.display().to_string();
+            let mut entries: Vec<(String, Node)> = std::mem::take(&mut included.nodes).into_iter().collect();
+            entries.sort_by(|a: &(String, Node), b: &(String, Node)| a.0.cmp(&b.0));
+            for (id, mut node) in entries {
+                let prefixed: String = format!("{}.{}", namespace, id);
+                if self.nodes.contains_key(&prefixed) {
+                    let existing: String = match (self.source_map.file(&prefixed), self.inline_origin(&prefixed)) {
+                        (Some(other), _) => format!("the node of that name included from '{}'", other),
+                        (None, Some((host, port))) => format!("the inline operation in node '{}' port '{}'", host, port),
+                        (None, None) => "the node of that name".to_string(),
+                    };
+                    return Err(fail(format!("node '{}' is included as '{}', which collides with {}", id, prefixed, existing)));
+                }
+
+                if let Some(span) = included.source_map.get(&id) {
+                    let _: Option<Span> = self.source_map.spans.insert(prefixed.clone(), span);
+                }
+                let origin: String = included.source_map.file(&id).map_or_else(|| file.clone(), str::to_string);
+                let _: Option<String> = self.source_map.files.insert(prefixed.clone(), origin);
+
+                node.prefix_references(&namespace);
+                let _: Option<Node> = self.nodes.insert(prefixed, node);
+            }
+        }
+
+        let _: Option<PathBuf> = chain.pop();
+        Ok(())
+    }
+
+    /// The node and port whose inline operation was hoisted into node `id`.
+    fn inline_origin<'a>(&self, id: &'a str) -> Option<(&'a str, &'a str)> {
+        id.match_indices('.').find_map(|(i, _): (usize, &str)| {
+            let (host, port): (&str, &str) = (&id[..i], &id[i + 1..]);
+            let reference: &str = self.nodes.get(host)?.args.get(port)?.get("ref")?.as_str()?;
+            (reference == id).then_some((host, port))
+        })
+    }
+
+    /// Checks that every input's `type` annotation names a known type, so a
+    /// typo is reported up front rather than when the node executes.
+    pub fn check_types(&self) -> Result<()> {
+        let mut ids: Vec<&String> = self.nodes.keys().collect();
+        ids.sort();
+
+        for id in ids {
+            let mut ports: Vec<(&String, &serde_json::Value)> = self.nodes[id].args.iter().collect();
+            ports.sort_by(|a: &(&String, &serde_json::Value), b: &(&String, &serde_json::Value)| a.0.cmp(b.0));
+
+            for (port, value) in ports {
+                if let Some(text) = Node::unknown_type(value) {
+                    return Err(Error::UnknownType {
+                        node: id.clone(),
+                        port: port.clone(),
+                        text: text.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the graph back to pretty-printed JSON in the source format.
+    /// Keys are emitted in sorted order so output is stable across runs.
+    /// Unresolved `include` directives are kept. Inline operations are written
+    /// in their hoisted form, as `<node>.<port>` nodes referenced by type, which
+    /// parses back to the same graph.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_value(self)
+            .and_then(|v: serde_json::Value| serde_json::to_string_pretty(&v))
+            .map_err(|e: serde_json::Error| Error::Serialization(e.to_string()))
+    }
+
+    /// Returns node ids in dependency order: every node appears after the nodes
+    /// it references. Ties are broken by node id. Cycles are not reported here.
+    pub fn topological_order(&self) -> Vec<String> {
+        fn visit(graph: &Graph, id: &str, seen: &mut HashSet<String>, order: &mut Vec<String>) {
+            if !seen.insert(id.to_string()) {
+                return;
+            }
+            if let Some(node) = graph.nodes.get(id) {
+                for dep in node.dependencies() {
+                    visit(graph, &dep, seen, order);
+                }
+                order.push(id.to_string());
+            }
+        }
+
+        let mut ids: Vec<&String> = self.nodes.keys().collect();
+        ids.sort();
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut order: Vec<String> = Vec::with_capacity(ids.len());
+        for id in ids {
+            visit(self, id, &mut seen, &mut order);
+        }
+        order
+    }
+
+    /// Returns the ids of nodes no other node references, in topological order.
+    pub fn sinks(&self) -> Vec<String> {
+        let referenced: HashSet<String> = self.nodes.values()
+            .flat_map(|node: &Node| node.dependencies())
+            .collect();
+        self.topological_order()
+            .into_iter()
+            .filter(|id: &String| !referenced.contains(id))
+            .collect()
+    }
+
+    /// Renders the graph as a GraphViz DOT digraph.
+    /// Nodes are labeled with their op; edges run from each referenced node to
+    /// the consuming port, naming the source port when it is not `out`.
+    pub fn to_dot(&self) -> String {
+        let escape = |s: &str| -> String { s.replace('\\', "\\\\").replace('"', "\\\"") };
+
+        let mut ids: Vec<&String> = self.nodes.keys().collect();
+        ids.sort();
+
+        let mut dot: String = String::from("digraph spell {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        for id in &ids {
+            let node: &Node = &self.nodes[*id];
+            let desc: String = node.desc.as_deref().map(|d: &str| format!("\\n{}", escape(d))).unwrap_or_default();
+            dot.push_str(&format!("    \"{}\" [label=\"{}\\n{}{}\"];\n", escape(id), escape(id), escape(&node.op), desc));
+        }
+
+        for id in &ids {
+            let node: &Node = &self.nodes[*id];
+            let ports: Vec<(String, TypedValue)> = node.get_all_typed_args()
+                .into_iter()
+                .filter_map(|(port, typed): (String, Result<TypedValue>)| typed.ok().map(|t: TypedValue| (port, t)))
+                .collect();
+
+            for (port, typed) in ports {
+                let Some(reference) = typed.get_reference() else { continue };
+                let (source, label): (&str, String) = match reference.split_once(':') {
+                    Some((source, src_port)) => (source, format!("{} → {}", src_port, port)),
+                    None => (reference, port.clone()),
+                };
+                dot.push_str(&format!("    \"{}\" -> \"{}\" [label=\"{}\"];\n", escape(source), escape(id), escape(&label)));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders how `node_id` is computed as an indented tree of its inputs.
+    /// Nodes referenced more than once are marked `[shared]` and expanded only
+    /// at their first appearance; references back into the current path are
+    /// shown as cycles instead of being followed.
+    pub fn render_tree(&self, node_id: &str) -> String {
+        // Count references among the nodes reachable from the root
+        let mut uses: HashMap<String, usize> = HashMap::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = vec![node_id.to_string()];
+        while let Some(id) = stack.pop() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&id) else { continue };
+            for (_, typed) in node.get_all_typed_args() {
+                if let Some(reference) = typed.ok().as_ref().and_then(TypedValue::get_reference) {
+                    let source: String = reference.split(':').next().unwrap_or(reference).to_string();
+                    *uses.entry(source.clone()).or_default() += 1;
+                    stack.push(source);
+                }
+            }
+        }
+
+        let mut out: String = String::new();
+        let mut path: Vec<String> = Vec::new();
+        let mut expanded: HashSet<String> = HashSet::new();
+        self.render_subtree(node_id, "", "", &uses, &mut path, &mut expanded, &mut out);
+        out
+    }
+
+    /// Appends one node's line (after `label`) and its inputs indented by `indent`.
+    #[allow(clippy::too_many_arguments)]
+    fn render_subtree(
+        &self,
+        reference: &str,
+        label: &str,
+        indent: &str,
+        uses: &HashMap<String, usize>,
+        path: &mut Vec<String>,
+        expanded: &mut HashSet<String>,
+        out: &mut String,
+    ) {
+        let id: &str = reference.split(':').next().unwrap_or(reference);
+        out.push_str(label);
+        out.push_str(reference);
+
+        let Some(node) = self.nodes.get(id) else {
+            out.push_str(" (not found)\n");
+            return;
+        };
+        out.push_str(&format!(" ({})", node.op));
+        if let Some(ref desc) = node.desc {
+            out.push_str(&format!(" — {}", desc));
+        }
+
+        if path.iter().any(|p: &String| p == id) {
+            out.push_str(" ↺ cycle\n");
+            return;
+        }
+        let shared: bool = uses.get(id).copied().unwrap_or(0) > 1;
+        if shared && !expanded.insert(id.to_string()) {
+            out.push_str(" [shared, see above]\n");
+            return;
+        }
+        out.push_str(if shared { " [shared]\n" } else { "\n" });
+
+        path.push(id.to_string());
+        let inputs: BTreeMap<String, Result<TypedValue>> = node.get_all_typed_args();
+        let count: usize = inputs.len();
+        for (i, (port, typed)) in inputs.into_iter().enumerate() {
+            let last: bool = i + 1 == count;
+            let branch: String = format!("{}{}{}: ", indent, if last { "└── " } else { "├── " }, port);
+            let child_indent: String = format!("{}{}", indent, if last { "    " } else { "│   " });
+            match typed {
+                Ok(TypedValue::Reference { reference, .. }) => {
+                    self.render_subtree(&reference, &branch, &child_indent, uses, path, expanded, out);
+                }
+                Ok(TypedValue::Literal { literal, .. }) => {
+                    out.push_str(&format!("{}{}\n", branch, Node::abbreviate(&literal)));
+                }
+                Err(_) => {
+                    let raw: &serde_json::Value = &node.args[&port];
+                    out.push_str(&format!("{}{} (untyped)\n", branch, Node::abbreviate(raw)));
+                }
+            }
+        }
+        let _: Option<String> = path.pop();
+    }
+}
+
+/// Removes `//` line comments and `/* */` block comments from JSON source.
+/// Comment-like text inside string literals is preserved. Comments are replaced
+/// by whitespace (keeping newlines) so error line/column positions still match.
+pub fn strip_comments(src: &str) -> String {
+    let mut out: String = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let mut in_string: bool = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                let _: Option<char> = chars.next();
+                out.push_str("  ");
+                let mut prev: char = ' ';
+                for next in chars.by_ref() {
+                    if prev == '*' && next == '/' {
+                        out.push(' ');
+                        break;
+                    }
+                    out.push(if next == '\n' { '\n' } else { ' ' });
+                    prev = next;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub op: String,
 
-    #[serde(default)]
-    pub returns: Option<SpellType>,
+    /// Free-text description shown by `--dot` and `--tree`; never an input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desc: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<Returns>,
+
+    /// `false` keeps this node's outputs out of the cache even when its
+    /// operation is cacheable, so every reference re-executes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<bool>,
+
+    /// Re-executes the operation when it fails with a transient error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
 
     #[serde(flatten)]
     pub args: HashMap<String, serde_json::Value>,
 }
 
+/// How often to retry a node whose operation fails with an `OperationError`
+/// or `Io` error. The wait before retry `n` (1-based) is `backoff_ms * 2^(n-1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first.
+    pub attempts: u32,
+    #[serde(default)]
+    pub backoff_ms: u64,
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry (1 for the first retry), saturating on overflow.
+    pub fn delay(&self, retry: u32) -> std::time::Duration {
+        let factor: u64 = 1_u64.checked_shl(retry.saturating_sub(1)).unwrap_or(u64::MAX);
+        std::time::Duration::from_millis(self.backoff_ms.saturating_mul(factor))
+    }
+}
+
+/// Declared output type(s) of a node: a single type for the `out` port, or
+/// a map from output port name to type (e.g. `{"true": ..., "false": ...}`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Returns {
+    Single(SpellType),
+    Ports(BTreeMap<String, SpellType>),
+}
+
+impl Returns {
+    /// The declared type of one output port, if any.
+    pub fn for_port(&self, port: &str) -> Option<&SpellType> {
+        match self {
+            Returns::Single(t) if port == "out" => Some(t),
+            Returns::Single(_) => None,
+            Returns::Ports(ports) => ports.get(port),
+        }
+    }
+
+    /// Every declared `(port, type)` pair, sorted by port.
+    pub fn ports(&self) -> Vec<(&str, &SpellType)> {
+        match self {
+            Returns::Single(t) => vec![("out", t)],
+            Returns::Ports(ports) => ports.iter()
+                .map(|(port, t): (&String, &SpellType)| (port.as_str(), t))
+                .collect(),
+        }
+    }
+}
+
+/// Deserialized by hand so an unknown type name reports which one, rather than
+/// serde's generic untagged-enum error.
+impl<'de> Deserialize<'de> for Returns {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(s) => SpellType::parse(&s).map(Returns::Single).map_err(de::Error::custom),
+            serde_json::Value::Object(map) => map.into_iter()
+                .map(|(port, t): (String, serde_json::Value)| -> std::result::Result<(String, SpellType), D::Error> {
+                    let name: &str = t.as_str()
+                        .ok_or_else(|| de::Error::custom(format!("return type of port '{}' must be a string", port)))?;
+                    SpellType::parse(name).map(|t: SpellType| (port, t)).map_err(de::Error::custom)
+                })
+                .collect::<std::result::Result<BTreeMap<String, SpellType>, D::Error>>()
+                .map(Returns::Ports),
+            other => Err(de::Error::custom(format!("expected a type or a map of port types, got {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for Returns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Returns::Single(t) => write!(f, "{}", t),
+            Returns::Ports(ports) => {
+                let parts: Vec<String> = ports.iter()
+                    .map(|(port, t): (&String, &SpellType)| format!("{}: {}", port, t))
+                    .collect();
+                write!(f, "{{{}}}", parts.join(", "))
+            }
+        }
+    }
+}
+
 impl Node {
-    pub fn get_all_typed_args(&self) -> HashMap<String, Result<TypedValue>> {
-        let mut result: HashMap<String, Result<TypedValue>> = HashMap::new();
+    /// Ids of the nodes this node references, without `:port` suffixes.
+    /// Sorted and deduplicated.
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut deps: Vec<String> = self.get_all_typed_args()
+            .into_values()
+            .filter_map(|typed: Result<TypedValue>| typed.ok())
+            .filter_map(|typed: TypedValue| {
+                typed.get_reference().map(|r: &str| r.split(':').next().unwrap_or(r).to_string())
+            })
+            .collect();
+        deps.sort();
+        deps.dedup();
+        deps
+    }
+
+    /// Prepends `<namespace>.` to every node this node references, keeping
+    /// any `:port` suffix. This covers the node ids that higher-order
+    /// operations take in `refs` (or `<prefix>_refs`) and in Pipe stages.
+    fn prefix_references(&mut self, namespace: &str) {
+        for (port, value) in self.args.iter_mut() {
+            if let Some(serde_json::Value::String(reference)) = value.get_mut("ref") {
+                *reference = format!("{}.{}", namespace, reference);
+                continue;
+            }
+            let raw: &mut serde_json::Value = match value.get("literal") {
+                Some(_) => &mut value["literal"],
+                None => value,
+            };
+            if port == "refs" || port.ends_with("_refs") {
+                Node::prefix_ref_map(raw, namespace);
+            } else if port == "ops" {
+                for stage in raw.as_array_mut().into_iter().flatten() {
+                    if let Some(refs) = stage.get_mut("refs") {
+                        Node::prefix_ref_map(refs, namespace);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Prepends `<namespace>.` to the node ids of a `refs` object.
+    fn prefix_ref_map(refs: &mut serde_json::Value, namespace: &str) {
+        for reference in refs.as_object_mut().into_iter().flat_map(|refs: &mut serde_json::Map<String, serde_json::Value>| refs.values_mut()) {
+            if let serde_json::Value::String(reference) = reference {
+                *reference = format!("{}.{}", namespace, reference);
+            }
+        }
+    }
+
+    /// Whether an argument is an inline operation: an object naming an `op`
+    /// rather than a `ref` or `literal`.
+    fn is_inline_op(value: &serde_json::Value) -> bool {
+        value.get("op").is_some_and(serde_json::Value::is_string)
+            && value.get("ref").is_none()
+            && value.get("literal").is_none()
+    }
+
+    /// The `type` string of an input annotation, if present and not a known type.
+    fn unknown_type(value: &serde_json::Value) -> Option<&str> {
+        let text: &str = value.get("type")?.as_str()?;
+        SpellType::parse(text).is_err().then_some(text)
+    }
+
+    /// Compact single-line rendering of a JSON value for display.
+    fn abbreviate(value: &serde_json::Value) -> String {
+        const MAX_LEN: usize = 40;
+        let rendered: String = value.to_string();
+        if rendered.chars().count() > MAX_LEN {
+            rendered.chars().take(MAX_LEN).collect::<String>() + "…"
+        } else {
+            rendered
+        }
+    }
+
+    /// Parses every input port's typed value, keyed (and so ordered) by port name.
+    pub fn get_all_typed_args(&self) -> BTreeMap<String, Result<TypedValue>> {
+        let mut result: BTreeMap<String, Result<TypedValue>> = BTreeMap::new();
         
         for (key, value) in &self.args {
-            if key == "op" || key == "returns" {
+            if key == "op" || key == "desc" || key == "returns" || key == "cache" || key == "retry" {
                 continue;
             }
             
-            let typed: Result<TypedValue> = serde_json::from_value::<TypedValue>(value.clone())
-                .map_err(|_| Error::MissingTypeAnnotation {
-                    node: "".to_string(),
-                    port: key.clone(),
+            // Deserializing from a borrow avoids copying large literals twice
+            let typed: Result<TypedValue> = TypedValue::deserialize(value)
+                .map_err(|_| match Node::unknown_type(value) {
+                    Some(text) => Error::UnknownType {
+                        node: "".to_string(),
+                        port: key.clone(),
+                        text: text.to_string(),
+                    },
+                    None => Error::MissingTypeAnnotation {
+                        node: "".to_string(),
+                        port: key.clone(),
+                    },
                 });
             
             let _: Option<Result<TypedValue>> = result.insert(key.clone(), typed);
@@ -46,3 +825,308 @@ impl Node {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Graph {
+        Graph::from_json_str(src).unwrap()
+    }
+
+    #[test]
+    fn to_dot_draws_nodes_and_reference_edges() {
+        let graph: Graph = parse(r#"{
+            "values": { "op": "Const", "value": { "literal": [1, 5], "type": "Array<Number>" } },
+            "split": { "op": "Route", "list": { "ref": "values", "type": "Array<Number>" }, "apply_op": { "literal": "Gt", "type": "String" } },
+            "big": { "op": "Len", "list": { "ref": "split:true", "type": "Array<Number>" } }
+        }"#);
+        let dot: String = graph.to_dot();
+        assert!(dot.starts_with("digraph spell {"), "{}", dot);
+        assert!(dot.contains("\"split\" [label=\"split\\nRoute\"]"), "{}", dot);
+        assert!(dot.contains("\"values\" -> \"split\" [label=\"list\"]"), "{}", dot);
+        assert!(dot.contains("\"split\" -> \"big\" [label=\"true → list\"]"), "{}", dot);
+    }
+
+    #[test]
+    fn to_json_round_trips_to_the_same_results() {
+        let outputs = |graph: Graph| -> Vec<(String, String)> {
+            crate::core::engine::Engine::new(graph)
+                .run_stream()
+                .map(|(id, result): (String, Result<serde_json::Value>)| (id, format!("{:?}", result)))
+                .collect()
+        };
+        for src in [
+            include_str!("../../examples/statistics.json"),
+            include_str!("../../examples/sales_analysis.json"),
+            include_str!("../../examples/temperature_conversion.json"),
+        ] {
+            let graph: Graph = parse(src);
+            let reparsed: Graph = parse(&graph.to_json().unwrap());
+            assert_eq!(serde_json::to_value(&reparsed).unwrap(), serde_json::to_value(&graph).unwrap());
+            assert_eq!(outputs(reparsed), outputs(graph));
+        }
+    }
+
+    #[test]
+    fn duplicate_node_ids_are_rejected() {
+        let src: &str = r#"{
+            "a": { "op": "Const", "value": { "literal": 1, "type": "Number" } },
+            "a": { "op": "Const", "value": { "literal": 2, "type": "Number" } }
+        }"#;
+        match Graph::from_json_str(src) {
+            Err(Error::Parse { reason, line, .. }) => {
+                assert!(reason.contains("duplicate node id: 'a'"), "{}", reason);
+                assert_eq!(line, 3);
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|g: Graph| g.nodes.len())),
+        }
+    }
+
+    #[test]
+    fn comments_are_ignored_outside_strings() {
+        let graph: Graph = parse(r#"{
+            // The greeting to print
+            "greeting": {
+                "op": "Const", /* inline note */
+                "value": { "literal": "see http://example.com // not a comment /* nor this */", "type": "String" }
+            }
+            /* trailing
+               block */
+        }"#);
+        assert_eq!(
+            graph.nodes["greeting"].args["value"]["literal"],
+            serde_json::json!("see http://example.com // not a comment /* nor this */")
+        );
+    }
+
+    #[test]
+    fn strip_comments_keeps_positions() {
+        let stripped: String = strip_comments("{ \"a\": 1, // note\n  \"b\": \"/*x*/\" /* gone */ }");
+        assert_eq!(stripped.lines().count(), 2);
+        let value: serde_json::Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(value, serde_json::json!({ "a": 1, "b": "/*x*/" }));
+    }
+
+    #[test]
+    fn array_form_behaves_like_map_form() {
+        let map_form: Graph = parse(r#"{
+            "x": { "op": "Const", "value": { "literal": 4, "type": "Number" } },
+            "y": { "op": "Sqrt", "in": { "ref": "x", "type": "Number" } }
+        }"#);
+        let array_form: Graph = parse(r#"{ "nodes": [
+            { "id": "x", "op": "Const", "value": { "literal": 4, "type": "Number" } },
+            { "id": "y", "op": "Sqrt", "in": { "ref": "x", "type": "Number" } }
+        ] }"#);
+        assert_eq!(serde_json::to_value(&array_form).unwrap(), serde_json::to_value(&map_form).unwrap());
+        for graph in [map_form, array_form] {
+            let results: Vec<(String, Result<serde_json::Value>)> = crate::core::engine::Engine::new(graph).run_stream().collect();
+            assert_eq!(format!("{:?}", results), format!("{:?}", vec![("y".to_string(), Ok::<serde_json::Value, Error>(serde_json::json!(2.0)))]));
+        }
+    }
+
+    #[test]
+    fn array_form_rejects_duplicate_ids() {
+        let err: Error = Graph::from_json_str(r#"{ "nodes": [ { "id": "x", "op": "Const" }, { "id": "x", "op": "Const" } ] }"#).unwrap_err();
+        assert!(err.to_string().contains("duplicate node id: 'x'"), "{}", err);
+    }
+
+    #[test]
+    fn tree_marks_shared_subtrees() {
+        let graph: Graph = parse(r#"{
+            "base": { "op": "Const", "value": { "literal": 2, "type": "Number" } },
+            "left": { "op": "Add", "a": { "ref": "base", "type": "Number" }, "b": { "literal": 1, "type": "Number" } },
+            "right": { "op": "Mul", "a": { "ref": "base", "type": "Number" }, "b": { "literal": 3, "type": "Number" } },
+            "top": { "op": "Add", "a": { "ref": "left", "type": "Number" }, "b": { "ref": "right", "type": "Number" } }
+        }"#);
+        let tree: String = graph.render_tree("top");
+        assert!(tree.starts_with("top (Add)\n"), "{}", tree);
+        assert_eq!(tree.matches("base (Const) [shared]\n").count(), 1, "{}", tree);
+        assert_eq!(tree.matches("base (Const) [shared, see above]").count(), 1, "{}", tree);
+    }
+
+    #[test]
+    fn tree_stops_at_cycles() {
+        let graph: Graph = parse(r#"{
+            "a": { "op": "Add", "a": { "ref": "b", "type": "Number" }, "b": { "literal": 1, "type": "Number" } },
+            "b": { "op": "Add", "a": { "ref": "a", "type": "Number" }, "b": { "literal": 1, "type": "Number" } }
+        }"#);
+        let tree: String = graph.render_tree("a");
+        assert!(tree.contains("↺ cycle"), "{}", tree);
+        assert_eq!(tree.lines().count(), 5, "{}", tree);
+    }
+
+    #[test]
+    fn misspelled_types_fail_at_parse_time() {
+        let err: Error = Graph::from_json_str(r#"{
+            "x": { "op": "Const", "value": { "literal": 1, "type": "Numbr" } }
+        }"#).unwrap_err();
+        match err {
+            Error::UnknownType { node, port, text } => {
+                assert_eq!((node.as_str(), port.as_str(), text.as_str()), ("x", "value", "Numbr"));
+            }
+            other => panic!("expected an unknown type, got {:?}", other),
+        }
+        assert!(Graph::from_json_str(r#"{ "x": { "op": "Const", "value": { "literal": [1], "type": "Array<Numbr>" } } }"#).is_err());
+    }
+
+    #[test]
+    fn to_json_writes_inline_ops_hoisted() {
+        let graph: Graph = parse(r#"{
+            "sum": { "op": "Add", "a": { "op": "Const", "value": 1, "type": "Number" }, "b": 2 }
+        }"#);
+        let json: String = graph.to_json().unwrap();
+        let reparsed: Graph = parse(&json);
+        assert!(reparsed.nodes.contains_key("sum.a"));
+        assert_eq!(reparsed.nodes["sum"].args["a"], serde_json::json!({ "ref": "sum.a", "type": "Number" }));
+        assert_eq!(reparsed.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn descriptions_label_nodes_but_are_not_inputs() {
+        let graph: Graph = parse(r#"{
+            "x": { "op": "Const", "desc": "the answer", "value": { "literal": 42, "type": "Number" } }
+        }"#);
+        let node: &Node = &graph.nodes["x"];
+        assert_eq!(node.desc.as_deref(), Some("the answer"));
+        assert_eq!(node.get_all_typed_args().keys().collect::<Vec<&String>>(), vec!["value"]);
+        assert!(graph.to_dot().contains("[label=\"x\\nConst\\nthe answer\"]"), "{}", graph.to_dot());
+        assert_eq!(graph.render_tree("x"), "x (Const) — the answer\n└── value: 42\n");
+    }
+
+    #[test]
+    fn node_errors_point_at_their_definition() {
+        let src: &str = r#"{
+      "a": { "op": "Const", "value": { "literal": "text", "type": "String" } },
+
+      "b": {
+        "op": "Len",
+        "list": { "ref": "a", "type": "Array<Number>" }
+      }
+    }"#;
+        let graph: Graph = parse(src);
+        let err: Error = crate::core::engine::Engine::new(graph.clone()).evaluate("b").unwrap_err();
+        let span: Span = graph.source_map.get(err.node().unwrap()).unwrap();
+        assert_eq!(span.line, 4);
+        assert_eq!(graph.source_map.get("a").unwrap().line, 2);
+    }
+
+    #[test]
+    fn included_nodes_are_referenced_with_a_prefix() {
+        let dir: PathBuf = fixture("include", &[
+            ("lib/helper.json", r#"{ "sum": { "op": "Add", "a": { "literal": 1, "type": "Number" }, "b": { "literal": 2, "type": "Number" } } }"#),
+            ("main.json", r#"{ "include": ["lib/helper.json"], "twice": { "op": "Mul", "a": { "ref": "helper.sum", "type": "Number" }, "b": { "literal": 2, "type": "Number" } } }"#),
+        ]);
+        let graph: Graph = load(&dir, "main.json").unwrap();
+        assert!(graph.nodes.contains_key("helper.sum"));
+        assert_eq!(crate::core::engine::Engine::new(graph).evaluate("twice").unwrap(), serde_json::json!(6));
+    }
+
+    #[test]
+    fn include_cycles_are_rejected() {
+        let dir: PathBuf = fixture("include-cycle", &[
+            ("a.json", r#"{ "include": ["b.json"] }"#),
+            ("b.json", r#"{ "include": ["a.json"] }"#),
+        ]);
+        let err: Error = load(&dir, "a.json").unwrap_err();
+        assert!(err.to_string().contains("cycle"), "{}", err);
+    }
+
+    /// A fresh directory under the system temp dir holding `files`.
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("spell-{}-{}", name, std::process::id()));
+        let _: std::io::Result<()> = fs::remove_dir_all(&dir);
+        for (file, contents) in files {
+            let path: PathBuf = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn load(dir: &Path, file: &str) -> Result<Graph> {
+        let path: PathBuf = dir.join(file);
+        let mut graph: Graph = parse(&fs::read_to_string(&path).unwrap());
+        graph.resolve_includes(&path).map(|()| graph)
+    }
+
+    #[test]
+    fn included_nodes_keep_their_own_spans() {
+        let dir: PathBuf = fixture("include-spans", &[
+            ("main.json", "{\n  \"include\": [\"helper.json\"],\n  \"total\": { \"op\": \"Add\", \"a\": { \"ref\": \"helper.sum\", \"type\": \"Number\" }, \"b\": 1 }\n}"),
+            ("helper.json", "{\n  \"x\": { \"op\": \"Const\", \"value\": 1 },\n\n  \"sum\": { \"op\": \"Add\", \"a\": { \"op\": \"Const\", \"value\": 2, \"type\": \"Number\" }, \"b\": 3 }\n}"),
+        ]);
+        let graph: Graph = load(&dir, "main.json").unwrap();
+        let helper: String = dir.join("helper.json").display().to_string();
+
+        assert_eq!(graph.source_map.get("total"), Some(Span { line: 3, column: 3 }));
+        assert_eq!(graph.source_map.file("total"), None);
+        assert_eq!(graph.source_map.get("helper.sum"), Some(Span { line: 4, column: 3 }));
+        assert_eq!(graph.source_map.file("helper.sum"), Some(helper.as_str()));
+        assert_eq!(graph.source_map.get("helper.sum.a"), Some(Span { line: 4, column: 3 }));
+        assert_eq!(graph.source_map.file("helper.sum.a"), Some(helper.as_str()));
+        assert_eq!(graph.source_map.get("helper.missing"), None);
+    }
+
+    #[test]
+    fn include_colliding_with_inline_operation_is_rejected() {
+        let dir: PathBuf = fixture("include-inline", &[
+            ("main.json", r#"{ "include": ["helper.json"], "helper": { "op": "Add", "a": 1, "sum": { "op": "Const", "value": 2, "type": "Number" } } }"#),
+            ("helper.json", r#"{ "sum": { "op": "Const", "value": 1 } }"#),
+        ]);
+        let err: String = load(&dir, "main.json").unwrap_err().to_string();
+        assert!(err.contains("node 'sum' is included as 'helper.sum', which collides with the inline operation in node 'helper' port 'sum'"), "{}", err);
+    }
+
+    #[test]
+    fn includes_sharing_a_file_stem_are_rejected() {
+        let dir: PathBuf = fixture("include-stem", &[
+            ("main.json", r#"{ "include": ["a/helper.json", "b/helper.json"] }"#),
+            ("a/helper.json", r#"{ "sum": { "op": "Const", "value": 1 } }"#),
+            ("b/helper.json", r#"{ "sum": { "op": "Const", "value": 2 } }"#),
+        ]);
+        let err: String = load(&dir, "main.json").unwrap_err().to_string();
+        assert!(err.contains("collides with the node of that name included from"), "{}", err);
+        assert!(err.contains("a/helper.json"), "{}", err);
+    }
+
+    #[test]
+    fn included_refs_are_prefixed() {
+        let dir: PathBuf = fixture("include-refs", &[
+            ("helper.json", r#"{
+                "two": { "op": "Const", "value": { "literal": 2, "type": "Number" } },
+                "list": { "op": "Const", "value": { "literal": [1, 2, 3], "type": "Array<Number>" } },
+                "scaled": {
+                    "op": "Map", "list": { "ref": "list", "type": "Array<Number>" },
+                    "apply_op": { "literal": "Mul", "type": "String" }, "arg": { "literal": "a", "type": "String" },
+                    "refs": { "literal": { "b": "two" }, "type": "Any" }
+                },
+                "big": {
+                    "op": "MapIf", "list": { "ref": "list", "type": "Array<Number>" },
+                    "predicate_op": { "literal": "Gt", "type": "String" }, "predicate_arg": { "literal": "a", "type": "String" },
+                    "predicate_refs": { "literal": { "b": "two:out" }, "type": "Any" },
+                    "map_op": { "literal": "Mul", "type": "String" }, "map_arg": { "literal": "a", "type": "String" },
+                    "map_refs": { "literal": { "b": "two" }, "type": "Any" }
+                },
+                "piped": {
+                    "op": "Pipe", "in": { "literal": 5, "type": "Number" },
+                    "ops": { "literal": [{ "op": "Add", "arg": "a", "refs": { "b": "two" } }], "type": "Any" }
+                }
+            }"#),
+            ("main.json", r#"{ "include": ["helper.json"] }"#),
+        ]);
+        let graph: Graph = load(&dir, "main.json").unwrap();
+        let mut engine: crate::core::engine::Engine = crate::core::engine::Engine::new(graph);
+        assert_eq!(engine.evaluate("helper.scaled").unwrap(), serde_json::json!([2, 4, 6]));
+        assert_eq!(engine.evaluate("helper.big").unwrap(), serde_json::json!([1, 2, 6]));
+        assert_eq!(engine.evaluate("helper.piped").unwrap(), serde_json::json!(7));
+    }
+
+    #[test]
+    fn to_json_keeps_unresolved_includes() {
+        let graph: Graph = parse(r#"{ "include": ["helper.json"], "a": { "op": "Const", "value": 1 } }"#);
+        let reparsed: Graph = parse(&graph.to_json().unwrap());
+        assert_eq!(reparsed.includes, vec!["helper.json".to_string()]);
+        assert!(reparsed.nodes.contains_key("a"));
+    }
+}
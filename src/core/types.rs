@@ -10,9 +10,17 @@ use std::fmt;
 #[serde(try_from = "String", into = "String")]
 pub enum SpellType {
     Number,
+    /// A number with no fractional part (`2` and `2.0`, but not `2.5`).
+    Integer,
     String,
     Boolean,
     Array(Box<SpellType>),
+    /// An array of exactly this many elements, written `Array<T; N>`.
+    SizedArray(Box<SpellType>, usize),
+    /// A value of the inner type, or null.
+    Optional(Box<SpellType>),
+    /// One of a fixed set of strings, written `Enum<"a", "b">`.
+    Enum(Vec<String>),
     Any,
     Unit,
 }
@@ -23,14 +31,35 @@ impl SpellType {
         
         match s {
             "Number" => Ok(SpellType::Number),
+            "Integer" => Ok(SpellType::Integer),
             "String" => Ok(SpellType::String),
             "Boolean" => Ok(SpellType::Boolean),
             "Any" => Ok(SpellType::Any),
             "Unit" => Ok(SpellType::Unit),
             _ if s.starts_with("Array<") && s.ends_with('>') => {
                 let inner: &str = &s[6..s.len()-1];
+                match split_length(inner) {
+                    Some((inner, len)) => {
+                        let len: usize = len.trim().parse::<usize>()
+                            .map_err(|_| format!("Array length must be a non-negative integer: '{}'", s))?;
+                        Ok(SpellType::SizedArray(Box::new(SpellType::parse(inner)?), len))
+                    }
+                    None => Ok(SpellType::Array(Box::new(SpellType::parse(inner)?))),
+                }
+            }
+            _ if s.starts_with("Optional<") && s.ends_with('>') => {
+                let inner: &str = &s[9..s.len()-1];
                 let inner_type: SpellType = SpellType::parse(inner)?;
-                Ok(SpellType::Array(Box::new(inner_type)))
+                Ok(SpellType::Optional(Box::new(inner_type)))
+            }
+            _ if s.starts_with("Enum<") && s.ends_with('>') => {
+                let members: &str = &s[5..s.len()-1];
+                let members: Vec<String> = serde_json::from_str(&format!("[{}]", members))
+                    .map_err(|_| format!("Enum members must be quoted strings: '{}'", s))?;
+                if members.is_empty() {
+                    return Err(format!("Enum must list at least one member: '{}'", s));
+                }
+                Ok(SpellType::Enum(members))
             }
             _ => Err(format!("Unknown type: '{}'", s)),
         }
@@ -39,27 +68,86 @@ impl SpellType {
     pub fn matches(&self, value: &serde_json::Value) -> bool {
         match (self, value) {
             (SpellType::Number, serde_json::Value::Number(_)) => true,
+            (SpellType::Integer, serde_json::Value::Number(n)) => {
+                n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f: f64| f.is_finite() && f.fract() == 0.0_f64)
+            }
             (SpellType::String, serde_json::Value::String(_)) => true,
             (SpellType::Boolean, serde_json::Value::Bool(_)) => true,
             (SpellType::Unit, serde_json::Value::Null) => true,
             (SpellType::Any, _) => true,
+            (SpellType::Optional(_), serde_json::Value::Null) => true,
+            (SpellType::Optional(inner), _) => inner.matches(value),
+            (SpellType::Enum(members), serde_json::Value::String(s)) => members.contains(s),
             (SpellType::Array(inner), serde_json::Value::Array(arr)) => {
                 arr.iter().all(|item: &serde_json::Value| inner.matches(item))
             }
+            (SpellType::SizedArray(inner, len), serde_json::Value::Array(arr)) => {
+                arr.len() == *len && arr.iter().all(|item: &serde_json::Value| inner.matches(item))
+            }
             _ => false,
         }
     }
+
+    /// Describes the runtime type of a value.
+    /// Arrays report their element type when homogeneous, `Array<Any>` otherwise.
+    pub fn of(value: &serde_json::Value) -> SpellType {
+        match value {
+            serde_json::Value::Number(_) => SpellType::Number,
+            serde_json::Value::String(_) => SpellType::String,
+            serde_json::Value::Bool(_) => SpellType::Boolean,
+            serde_json::Value::Null => SpellType::Unit,
+            serde_json::Value::Array(arr) => {
+                let mut types = arr.iter().map(SpellType::of);
+                let inner: SpellType = match types.next() {
+                    Some(first) if types.all(|t: SpellType| t == first) => first,
+                    _ => SpellType::Any,
+                };
+                SpellType::Array(Box::new(inner))
+            }
+            serde_json::Value::Object(_) => SpellType::Any,
+        }
+    }
+}
+
+/// Splits `T; N` at its length separator: the last `;` outside nested
+/// brackets and quoted enum members.
+fn split_length(inner: &str) -> Option<(&str, &str)> {
+    let mut depth: usize = 0;
+    let mut quoted: bool = false;
+    let mut escaped: bool = false;
+    let mut split: Option<usize> = None;
+    for (i, c) in inner.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '<' if !quoted => depth += 1,
+            '>' if !quoted => depth = depth.saturating_sub(1),
+            ';' if !quoted && depth == 0 => split = Some(i),
+            _ => {}
+        }
+    }
+    split.map(|i: usize| (&inner[..i], &inner[i + 1..]))
 }
 
 impl fmt::Display for SpellType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SpellType::Number => write!(f, "Number"),
+            SpellType::Integer => write!(f, "Integer"),
             SpellType::String => write!(f, "String"),
             SpellType::Boolean => write!(f, "Boolean"),
             SpellType::Any => write!(f, "Any"),
             SpellType::Unit => write!(f, "Unit"),
             SpellType::Array(inner) => write!(f, "Array<{}>", inner),
+            SpellType::SizedArray(inner, len) => write!(f, "Array<{}; {}>", inner, len),
+            SpellType::Optional(inner) => write!(f, "Optional<{}>", inner),
+            SpellType::Enum(members) => {
+                let quoted: Vec<String> = members.iter()
+                    .map(|m: &String| serde_json::Value::String(m.clone()).to_string())
+                    .collect();
+                write!(f, "Enum<{}>", quoted.join(", "))
+            }
         }
     }
 }
@@ -113,6 +201,21 @@ impl TypedValue {
         }
     }
 
+    /// Infers a typed literal from an unannotated argument value.
+    /// Accepts `{ "literal": value }` without a `type` or a bare non-object value.
+    /// References (and any other object) are never inferred.
+    pub fn infer_literal(value: &serde_json::Value) -> Option<TypedValue> {
+        let literal: &serde_json::Value = match value {
+            serde_json::Value::Object(obj) if obj.len() == 1 => obj.get("literal")?,
+            serde_json::Value::Object(_) => return None,
+            other => other,
+        };
+        Some(TypedValue::Literal {
+            literal: literal.clone(),
+            value_type: SpellType::of(literal),
+        })
+    }
+
     pub fn get_literal(&self) -> Option<&serde_json::Value> {
         match self {
             TypedValue::Literal { literal, .. } => Some(literal),
@@ -120,3 +223,64 @@ impl TypedValue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn integer_arrays_reject_fractions() {
+        let ints: SpellType = SpellType::parse("Array<Integer>").unwrap();
+        assert!(ints.matches(&json!([1, 2, 3])));
+        assert!(ints.matches(&json!([1.0, -4])));
+        assert!(!ints.matches(&json!([1, 2.5])));
+        assert!(ints.matches(&json!([])));
+    }
+
+    #[test]
+    fn integer_array_display_round_trips() {
+        for text in ["Integer", "Array<Integer>", "Array<Integer; 3>", "Optional<Array<Integer>>"] {
+            let parsed: SpellType = SpellType::parse(text).unwrap();
+            assert_eq!(parsed.to_string(), text);
+            assert_eq!(SpellType::parse(&parsed.to_string()).unwrap(), parsed);
+        }
+    }
+
+    #[test]
+    fn enums_match_only_their_members() {
+        let model: SpellType = SpellType::parse(r#"Enum<"gpt-4", "gpt-3.5">"#).unwrap();
+        assert!(model.matches(&json!("gpt-4")));
+        assert!(!model.matches(&json!("gpt-5")));
+        assert!(!model.matches(&json!(4)));
+        assert!(SpellType::parse("Enum<>").is_err());
+        assert!(SpellType::parse("Enum<gpt>").is_err());
+    }
+
+    #[test]
+    fn enums_round_trip_through_display() {
+        let model: SpellType = SpellType::Enum(vec!["a, b".to_string(), "say \"hi\"".to_string()]);
+        assert_eq!(SpellType::parse(&model.to_string()).unwrap(), model);
+        let nested: SpellType = SpellType::parse(r#"Array<Enum<"x">>"#).unwrap();
+        assert_eq!(SpellType::parse(&nested.to_string()).unwrap(), nested);
+    }
+
+    #[test]
+    fn sized_arrays_check_their_length() {
+        let pair: SpellType = SpellType::parse("Array<Number; 2>").unwrap();
+        assert!(pair.matches(&json!([1, 2])));
+        assert!(!pair.matches(&json!([1, 2, 3])));
+        assert!(!pair.matches(&json!([1, "2"])));
+        let any_length: SpellType = SpellType::parse("Array<Number>").unwrap();
+        assert!(any_length.matches(&json!([1, 2, 3])));
+        assert!(any_length.matches(&json!([])));
+    }
+
+    #[test]
+    fn sized_arrays_round_trip() {
+        let grid: SpellType = SpellType::parse("Array<Array<Number; 2>; 3>").unwrap();
+        assert_eq!(grid, SpellType::SizedArray(Box::new(SpellType::SizedArray(Box::new(SpellType::Number), 2)), 3));
+        assert_eq!(grid.to_string(), "Array<Array<Number; 2>; 3>");
+        assert!(SpellType::parse("Array<Number; -1>").is_err());
+    }
+}
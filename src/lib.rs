@@ -0,0 +1,32 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// SPELL
+// Copyright (c) 2025 Santino Research. MIT License.
+// ─────────────────────────────────────────────────────────────────────────────
+
+//! SPELL - dataflow programming for LLMs.
+//!
+//! A program is a [`Graph`] of nodes, each applying an [`Operation`] to typed
+//! inputs. An [`Engine`] evaluates nodes on demand using an [`Ops`] registry:
+//!
+//! ```
+//! use spell::Engine;
+//!
+//! let mut engine: Engine = Engine::from_json_str(r#"{
+//!     "sum": {
+//!         "op": "Add",
+//!         "a": { "literal": 1, "type": "Number" },
+//!         "b": { "literal": 2, "type": "Number" }
+//!     }
+//! }"#).unwrap();
+//! assert_eq!(engine.evaluate("sum").unwrap(), serde_json::json!(3));
+//! ```
+//!
+//! Graphs can also be loaded with [`Graph::from_json_str`] and passed to [`Engine::new`].
+
+pub mod core;
+
+pub use crate::core::engine::Engine;
+pub use crate::core::error::Error;
+pub use crate::core::ops::{Operation, Ops};
+pub use crate::core::schema::Graph;
+pub use crate::core::types::SpellType;
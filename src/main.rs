@@ -5,10 +5,11 @@
 
 //! SPELL command-line interface.
 
-mod core;
-
 use clap::Parser;
+use spell::core;
 use std::fs;
+use std::io::Write;
+use std::path::Path;
 use std::process;
 
 #[derive(Parser)]
@@ -17,17 +18,97 @@ use std::process;
 struct Cli {
     /// SPELL program file (.json)
     file: String,
+
+    /// Directory that file operations are confined to (defaults to the working directory)
+    #[arg(long)]
+    base_dir: Option<String>,
+
+    /// Allow HTTP operations to fetch non-HTTPS URLs and private addresses
+    #[arg(long)]
+    allow_insecure_http: bool,
+
+    /// Show per-element progress of Map/Filter/Reduce on stderr
+    #[arg(long)]
+    progress: bool,
+
+    /// Infer types of literals that omit a type annotation
+    #[arg(long)]
+    infer_literals: bool,
+
+    /// Print non-integral numbers with this many decimal places
+    #[arg(long)]
+    decimals: Option<usize>,
+
+    /// Drop trailing zeros from printed numbers (5.0 prints as 5)
+    #[arg(long)]
+    trim_zeros: bool,
+
+    /// Suppress the banner and other non-error output on stderr (also via SPELL_QUIET=1)
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Include extra diagnostics, such as an operation's inputs, in errors
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Print each sink node's result as soon as it completes
+    #[arg(long)]
+    stream: bool,
+
+    /// Print only the value of NODE as the program's result (graphs conventionally name it `@output`)
+    #[arg(long, value_name = "NODE")]
+    output: Option<String>,
+
+    /// Print each sink's result as a JSON line `{"node": ..., "value"|"error": ...}` as it completes
+    #[arg(long)]
+    ndjson: bool,
+
+    /// Print all sink results as one JSON object with sorted keys
+    #[arg(long)]
+    json: bool,
+
+    /// Print call counts and execution times per operation on stderr after running
+    #[arg(long)]
+    profile: bool,
+
+    /// Compare the program against another graph file and print the differences
+    #[arg(long, value_name = "OTHER")]
+    diff: Option<String>,
+
+    /// Provide a runtime value to `Input` nodes, parsed as JSON or else taken as a string
+    #[arg(long, value_name = "NAME=VALUE")]
+    input: Vec<String>,
+
+    /// Print each node's resolved inputs without running Print, HttpGet or other side effects
+    #[arg(long)]
+    explain: bool,
+
+    /// Report ports typed Any that could use a tighter type, instead of running the graph
+    #[arg(long)]
+    lint: bool,
+
+    /// Print an indented tree of how NODE is computed instead of running the graph
+    #[arg(long, value_name = "NODE")]
+    tree: Option<String>,
+
+    /// Print the graph as GraphViz DOT instead of running it
+    #[arg(long)]
+    dot: bool,
 }
 
 fn main() {
     let cli: Cli = Cli::parse();
+    let quiet: bool = cli.quiet
+        || std::env::var("SPELL_QUIET").is_ok_and(|v: String| !v.is_empty() && v != "0");
 
     // Banner
-    eprintln!("╔═══════════════════════════════════════╗");
-    eprintln!("║  SPELL v0.1 (pre-alpha)               ║");
-    eprintln!("║  Santino Research                     ║");
-    eprintln!("╚═══════════════════════════════════════╝");
-    eprintln!();
+    if !quiet {
+        eprintln!("╔═══════════════════════════════════════╗");
+        eprintln!("║  SPELL v0.1 (pre-alpha)               ║");
+        eprintln!("║  Santino Research                     ║");
+        eprintln!("╚═══════════════════════════════════════╝");
+        eprintln!();
+    }
 
     let content: String = match fs::read_to_string(&cli.file) {
         Ok(c) => c,
@@ -37,14 +118,202 @@ fn main() {
         }
     };
 
-    let graph: core::schema::Graph = match serde_json::from_str(&content) {
+    // With --verbose, node-level errors also point at the node's definition
+    let locate = |e: &core::error::Error, source_map: &core::schema::SourceMap| {
+        if let Some(node) = e.node() {
+            if let Some(span) = source_map.get(node) {
+                eprintln!("  --> {}:{}", source_map.file(node).unwrap_or(&cli.file), span);
+            }
+        }
+    };
+
+    let mut graph: core::schema::Graph = match core::schema::Graph::from_json_str(&content) {
         Ok(g) => g,
         Err(e) => {
             eprintln!("Error: {}", e);
+            if cli.verbose {
+                locate(&e, &core::schema::SourceMap::from_source(&content));
+            }
             process::exit(1);
         }
     };
+    if let Err(e) = graph.resolve_includes(Path::new(&cli.file)) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+    let source_map: core::schema::SourceMap = graph.source_map.clone();
+
+    if let Some(ref other_file) = cli.diff {
+        let mut other: core::schema::Graph = match fs::read_to_string(other_file)
+            .map_err(|e: std::io::Error| e.to_string())
+            .and_then(|c: String| core::schema::Graph::from_json_str(&c).map_err(|e: core::error::Error| e.to_string()))
+        {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        };
+        if let Err(e) = other.resolve_includes(Path::new(other_file)) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        print!("{}", graph.diff(&other));
+        return;
+    }
+
+    if let Some(ref node_id) = cli.tree {
+        print!("{}", graph.render_tree(node_id));
+        return;
+    }
+
+    if cli.dot {
+        print!("{}", graph.to_dot());
+        return;
+    }
 
     let mut engine: core::engine::Engine = core::engine::Engine::new(graph);
-    engine.run();
+    if let Some(dir) = cli.base_dir {
+        engine = engine.with_base_dir(dir);
+    }
+    engine = engine.with_insecure_http(cli.allow_insecure_http);
+    engine = engine.with_literal_inference(cli.infer_literals);
+    engine = engine.with_verbose_errors(cli.verbose);
+    engine = engine.with_number_format(core::format::NumberFormat {
+        decimals: cli.decimals,
+        trim_trailing_zeros: cli.trim_zeros,
+    });
+    for assignment in &cli.input {
+        let Some((name, raw)) = assignment.split_once('=') else {
+            eprintln!("Error: --input expects NAME=VALUE, got '{}'", assignment);
+            process::exit(1);
+        };
+        let value: serde_json::Value = serde_json::from_str(raw)
+            .unwrap_or_else(|_| serde_json::Value::String(raw.to_string()));
+        engine.set_input(name, value);
+    }
+    if cli.progress && !quiet {
+        engine = engine.with_progress(|node_id: &str, processed: usize, total: usize| {
+            const WIDTH: usize = 30;
+            let filled: usize = (processed * WIDTH).checked_div(total).unwrap_or(WIDTH);
+            eprint!("\r{} [{}{}] {}/{}", node_id, "#".repeat(filled), "-".repeat(WIDTH - filled), processed, total);
+            if processed == total {
+                eprintln!();
+            }
+        });
+    }
+    if cli.lint {
+        for warning in engine.lint() {
+            println!("warning: {}", warning);
+        }
+        return;
+    }
+
+    let verbose: bool = cli.verbose;
+    let report = |e: &core::error::Error| {
+        if verbose {
+            eprintln!("Error: {:#}", e);
+            locate(e, &source_map);
+        } else {
+            eprintln!("Error: {}", e);
+        }
+    };
+
+    // Report problems such as misspelled operations before anything runs
+    if let Err(e) = engine.validate() {
+        report(&e);
+        process::exit(1);
+    }
+
+    if cli.explain {
+        const MAX_VALUE_LEN: usize = 60;
+        println!("{:<20} {:<12} {:<16} value", "node", "port", "type");
+        for explanation in engine.explain() {
+            let node: String = format!("{} ({})", explanation.node, explanation.op);
+            if explanation.inputs.is_empty() {
+                println!("{:<20} {:<12} {:<16} -", node, "-", "-");
+            }
+            for (port, declared, value) in explanation.inputs {
+                let declared: String = declared.map_or_else(|| "?".to_string(), |t: core::types::SpellType| t.to_string());
+                let rendered: String = match value {
+                    Ok(val) if val.to_string().chars().count() > MAX_VALUE_LEN => {
+                        val.to_string().chars().take(MAX_VALUE_LEN).collect::<String>() + "…"
+                    }
+                    Ok(val) => val.to_string(),
+                    Err(e) => format!("error: {}", e),
+                };
+                println!("{:<20} {:<12} {:<16} {}", node, port, declared, rendered);
+            }
+        }
+        return;
+    }
+
+    let mut failed: bool = false;
+    if let Some(node_id) = cli.output {
+        engine = engine.with_output(node_id);
+        match engine.result() {
+            Ok(val) => println!("{}", val),
+            Err(e) => {
+                report(&e);
+                failed = true;
+            }
+        }
+    } else if cli.json {
+        let mut results: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
+        for (node_id, result) in engine.run_stream() {
+            match result {
+                Ok(val) => {
+                    let _: Option<serde_json::Value> = results.insert(node_id, val);
+                }
+                Err(e) => {
+                    report(&e);
+                    failed = true;
+                }
+            }
+        }
+        println!("{}", serde_json::Value::Object(results));
+    } else if cli.ndjson {
+        let mut stdout: std::io::StdoutLock<'static> = std::io::stdout().lock();
+        for (node_id, result) in engine.run_stream() {
+            failed |= result.is_err();
+            let record: serde_json::Value = match result {
+                Ok(val) => serde_json::json!({ "node": node_id, "value": val }),
+                Err(e) if verbose => serde_json::json!({ "node": node_id, "error": format!("{:#}", e) }),
+                Err(e) => serde_json::json!({ "node": node_id, "error": e.to_string() }),
+            };
+            if writeln!(stdout, "{}", record).and_then(|_| stdout.flush()).is_err() {
+                process::exit(1);
+            }
+        }
+    } else if cli.stream {
+        for (node_id, result) in engine.run_stream() {
+            match result {
+                Ok(val) => println!("{}: {}", node_id, val),
+                Err(e) => {
+                    report(&e);
+                    failed = true;
+                }
+            }
+        }
+    } else {
+        for e in engine.run_collect() {
+            report(&e);
+            failed = true;
+        }
+    }
+
+    if cli.profile {
+        let mut rows: Vec<(&String, &core::metrics::OpMetrics)> = engine.metrics().iter().collect();
+        rows.sort_by(|a: &(&String, &core::metrics::OpMetrics), b: &(&String, &core::metrics::OpMetrics)| {
+            b.1.total.cmp(&a.1.total).then_with(|| a.0.cmp(b.0))
+        });
+        eprintln!("{:<16} {:>8} {:>14} {:>14}", "op", "calls", "total", "mean");
+        for (op, m) in rows {
+            eprintln!("{:<16} {:>8} {:>14} {:>14}", op, m.calls, format!("{:.3?}", m.total), format!("{:.3?}", m.mean()));
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
 }
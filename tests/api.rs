@@ -0,0 +1,29 @@
+//! Tests that use SPELL only through the crate's public re-exports.
+
+use spell::{Engine, Error, Graph, Operation, Ops, SpellType};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+struct Shout;
+
+impl Operation for Shout {
+    fn execute(&self, inputs: &HashMap<String, Value>) -> spell::core::error::Result<HashMap<String, Value>> {
+        let text: String = inputs["in"].as_str().unwrap_or_default().to_uppercase() + "!";
+        Ok(HashMap::from([("out".to_string(), Value::String(text))]))
+    }
+}
+
+#[test]
+fn graphs_evaluate_through_the_public_api() {
+    let graph: Graph = Graph::from_json_str(r#"{
+        "word": { "op": "Const", "value": { "literal": "hello", "type": "String" } },
+        "loud": { "op": "Shout", "in": { "ref": "word", "type": "String" }, "returns": "String" }
+    }"#).unwrap();
+    let mut ops: Ops = Ops::default();
+    ops.register("Shout", Shout);
+    let mut engine: Engine = Engine::new(graph).with_ops(ops);
+    let value: Value = engine.evaluate("loud").unwrap();
+    assert_eq!(value, json!("HELLO!"));
+    assert!(SpellType::parse("String").unwrap().matches(&value));
+    assert!(matches!(engine.evaluate("missing"), Err(Error::NodeNotFound(_))));
+}
@@ -0,0 +1,116 @@
+//! End-to-end tests of the `spell` binary.
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Writes `graph` to a fresh file named after the test.
+fn program(name: &str, graph: &str) -> PathBuf {
+    let path: PathBuf = std::env::temp_dir().join(format!("spell-cli-{}-{}.json", name, std::process::id()));
+    std::fs::write(&path, graph).unwrap();
+    path
+}
+
+fn spell(args: &[&str], file: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_spell"))
+        .arg(file)
+        .args(args)
+        .env_remove("SPELL_QUIET")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn quiet_runs_keep_stderr_for_errors() {
+    let ok: PathBuf = program("quiet-ok", r#"{ "x": { "op": "Const", "value": { "literal": 1, "type": "Number" } } }"#);
+    let out: Output = spell(&["--quiet"], &ok);
+    assert!(out.status.success());
+    assert!(out.stderr.is_empty(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(!spell(&[], &ok).stderr.is_empty());
+
+    let bad: PathBuf = program("quiet-bad", r#"{ "x": { "op": "Div", "a": { "literal": 1, "type": "Number" }, "b": { "literal": 0, "type": "Number" } } }"#);
+    let out: Output = spell(&["-q"], &bad);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).starts_with("Error:"));
+}
+
+#[test]
+fn quiet_env_var_hides_the_banner() {
+    let ok: PathBuf = program("quiet-env", r#"{ "x": { "op": "Const", "value": { "literal": 1, "type": "Number" } } }"#);
+    let out: Output = Command::new(env!("CARGO_BIN_EXE_spell")).arg(&ok).env("SPELL_QUIET", "1").output().unwrap();
+    assert!(out.stderr.is_empty(), "{}", String::from_utf8_lossy(&out.stderr));
+}
+
+#[test]
+fn repeated_runs_print_identically() {
+    let mut graph: String = String::from("{");
+    for i in 0..12 {
+        graph.push_str(&format!(
+            r#""p{i}": {{ "op": "Print", "label": {{ "literal": "n{i}", "type": "String" }}, "in": {{ "literal": {i}, "type": "Number" }} }},"#
+        ));
+    }
+    graph.push_str(r#""z": { "op": "Const", "value": { "literal": 0, "type": "Number" } } }"#);
+    let file: PathBuf = program("stable", &graph);
+
+    for args in [&["-q"][..], &["-q", "--json"][..]] {
+        let first: Vec<u8> = spell(args, &file).stdout;
+        assert!(!first.is_empty());
+        for _ in 0..2 {
+            assert_eq!(spell(args, &file).stdout, first);
+        }
+    }
+    let json: String = String::from_utf8(spell(&["-q", "--json"], &file).stdout).unwrap();
+    let keys: Vec<&str> = json.match_indices("\"p").map(|(i, _): (usize, &str)| &json[i..i + 4]).collect();
+    let mut sorted: Vec<&str> = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+}
+
+#[test]
+fn ndjson_prints_one_record_per_sink() {
+    let file: PathBuf = program("ndjson", r#"{
+        "x": { "op": "Const", "value": { "literal": 2, "type": "Number" } },
+        "double": { "op": "Mul", "a": { "ref": "x", "type": "Number" }, "b": { "literal": 2, "type": "Number" } },
+        "broken": { "op": "Div", "a": { "ref": "x", "type": "Number" }, "b": { "literal": 0, "type": "Number" } }
+    }"#);
+    let out: Output = spell(&["-q", "--ndjson"], &file);
+    assert!(!out.status.success());
+    let records: Vec<serde_json::Value> = String::from_utf8(out.stdout).unwrap()
+        .lines()
+        .map(|line: &str| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["node"], "broken");
+    assert!(records[0]["error"].is_string());
+    assert_eq!(records[1], serde_json::json!({ "node": "double", "value": 4 }));
+}
+
+#[test]
+fn print_label_and_format_are_configurable() {
+    let file: PathBuf = program("print", r#"{
+        "default": { "op": "Print", "in": { "literal": 1.5, "type": "Number" } },
+        "labelled": { "op": "Print", "in": { "literal": "hi", "type": "String" }, "label": { "literal": "greeting", "type": "String" } },
+        "plain": { "op": "Print", "in": { "literal": "hi", "type": "String" }, "label": { "literal": "", "type": "String" } },
+        "structured": {
+            "op": "Print",
+            "in": { "literal": { "a": [1, "x"] }, "type": "Any" },
+            "label": { "literal": "", "type": "String" },
+            "format": { "literal": "json", "type": "String" }
+        }
+    }"#);
+    let out: Output = spell(&["-q"], &file);
+    assert!(out.status.success());
+    let stdout: String = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(stdout, "OUTPUT: 1.5\ngreeting: \"hi\"\n\"hi\"\n{\"a\":[1,\"x\"]}\n");
+}
+
+#[test]
+fn failing_nodes_make_the_exit_status_nonzero() {
+    let file: PathBuf = program("exit", r#"{
+        "fine": { "op": "Const", "value": { "literal": 1, "type": "Number" } },
+        "broken": { "op": "Div", "a": { "literal": 1, "type": "Number" }, "b": { "literal": 0, "type": "Number" } }
+    }"#);
+    let out: Output = spell(&["-q"], &file);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("broken"));
+    assert_eq!(spell(&["-q", "--output", "fine"], &file).status.code(), Some(0));
+}